@@ -0,0 +1,247 @@
+/// Stores metadata for a part of a change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span<D> {
+    /// The length of this span.
+    pub length: usize,
+    /// The data associated with this span.
+    pub data: D,
+}
+
+impl<D: Clone> Span<D> {
+    /// Create a new span
+    pub fn new(length: usize, data: D) -> Self {
+        Span { length, data }
+    }
+
+    fn cut(&self, length: usize) -> Self {
+        Span::new(length, self.data.clone())
+    }
+
+    /// The total length of a list of spans
+    pub fn len(spans: &[Span<D>]) -> usize {
+        spans.iter().map(|span| span.length).sum()
+    }
+
+    pub(crate) fn slice(spans: &[Span<D>], from: usize, to: usize) -> Vec<Span<D>> {
+        let mut result = Vec::new();
+        if from == to {
+            return result;
+        }
+        let mut off = 0;
+        for span in spans {
+            if off >= to {
+                break;
+            }
+            let end = off + span.length;
+            let (start, stop) = (usize::max(from, off), usize::min(to, end));
+            if stop > start {
+                result.push(span.cut(stop - start));
+            }
+            off = end;
+        }
+        result
+    }
+
+    pub(crate) fn join<F>(mut a: Vec<Span<D>>, b: Vec<Span<D>>, combine: F) -> Vec<Span<D>>
+    where
+        F: Fn(&D, &D) -> Option<D>,
+    {
+        let mut b = b.into_iter();
+        if let (Some(last), Some(first)) = (a.last_mut(), b.as_slice().first()) {
+            if let Some(combined) = combine(&last.data, &first.data) {
+                *last = Span::new(last.length + first.length, combined);
+                b.next();
+            }
+        }
+        a.extend(b);
+        a
+    }
+}
+
+/// A replaced range with metadata associated with it.
+///
+/// Positions with an `a` suffix refer to the start document of the change set,
+/// positions with a `b` suffix to the current document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change<D> {
+    /// The start of the range deleted/replaced in the old document.
+    pub from_a: usize,
+    /// The end of the range in the old document.
+    pub to_a: usize,
+    /// The start of the range inserted in the new document.
+    pub from_b: usize,
+    /// The end of the range in the new document.
+    pub to_b: usize,
+    /// Data associated with the deleted content. The length of these spans
+    /// adds up to `to_a - from_a`.
+    pub deleted: Vec<Span<D>>,
+    /// Data associated with the inserted content. The length of these spans
+    /// adds up to `to_b - from_b`.
+    pub inserted: Vec<Span<D>>,
+}
+
+impl<D: Clone> Change<D> {
+    /// The length of the range in the old document
+    pub fn len_a(&self) -> usize {
+        self.to_a - self.from_a
+    }
+
+    /// The length of the range in the new document
+    pub fn len_b(&self) -> usize {
+        self.to_b - self.from_b
+    }
+
+    pub(crate) fn slice(&self, start_a: usize, end_a: usize, start_b: usize, end_b: usize) -> Self {
+        if start_a == 0 && start_b == 0 && end_a == self.len_a() && end_b == self.len_b() {
+            return self.clone();
+        }
+        Change {
+            from_a: self.from_a + start_a,
+            to_a: self.from_a + end_a,
+            from_b: self.from_b + start_b,
+            to_b: self.from_b + end_b,
+            deleted: Span::slice(&self.deleted, start_a, end_a),
+            inserted: Span::slice(&self.inserted, start_b, end_b),
+        }
+    }
+
+    /// Merge two sets of changes, where the end document of `x` is the start
+    /// document of `y`, into a single set spanning from the start of `x` to
+    /// the end of `y`.
+    ///
+    /// Deletions from `x` and insertions from `y` are kept. Content inserted
+    /// by `x` and deleted by `y` disappears.
+    pub fn merge<F>(x: &[Change<D>], y: &[Change<D>], combine: F) -> Vec<Change<D>>
+    where
+        F: Fn(&D, &D) -> Option<D>,
+    {
+        if x.is_empty() {
+            return y.to_vec();
+        }
+        if y.is_empty() {
+            return x.to_vec();
+        }
+
+        // The size difference of the change before `i` in `changes`
+        fn offset<D: Clone>(changes: &[Change<D>], i: usize) -> isize {
+            match i {
+                0 => 0,
+                _ => changes[i - 1].to_b as isize - changes[i - 1].to_a as isize,
+            }
+        }
+        let shift = |pos: usize, off: isize| (pos as isize + off) as usize;
+
+        // Iterate over both sets in parallel, using the middle coordinate
+        // system (B in x, A in y) to synchronize.
+        let mut result = Vec::new();
+        let (mut i_x, mut i_y) = (0, 0);
+        loop {
+            let (cur_x, cur_y) = (x.get(i_x), y.get(i_y));
+            match (cur_x, cur_y) {
+                (None, None) => return result,
+                (Some(cx), _) if !matches!(cur_y, Some(cy) if cy.from_a <= cx.to_b) => {
+                    // `cx` is entirely in front of `cy`
+                    let off = offset(y, i_y);
+                    result.push(Change {
+                        from_b: shift(cx.from_b, off),
+                        to_b: shift(cx.to_b, off),
+                        ..cx.clone()
+                    });
+                    i_x += 1;
+                }
+                (_, Some(cy)) if !matches!(cur_x, Some(cx) if cx.from_b <= cy.to_a) => {
+                    // `cy` is entirely in front of `cx`
+                    let off = offset(x, i_x);
+                    result.push(Change {
+                        from_a: shift(cy.from_a, -off),
+                        to_a: shift(cy.to_a, -off),
+                        ..cy.clone()
+                    });
+                    i_y += 1;
+                }
+                (Some(cx), Some(cy)) => {
+                    // The ranges touch, so they need to be merged. Areas of the middle
+                    // document covered by `x` but not by `y` are insertions from `x`,
+                    // areas covered by `y` but not `x` are deletions from `y`.
+                    let mut pos = usize::min(cx.from_b, cy.from_a);
+                    let from_a = usize::min(cx.from_a, shift(cy.from_a, -offset(x, i_x)));
+                    let from_b = usize::min(cy.from_b, shift(cx.from_b, offset(y, i_y)));
+                    let (mut to_a, mut to_b) = (from_a, from_b);
+                    let mut deleted = Vec::new();
+                    let mut inserted = Vec::new();
+
+                    // Prevents appending the ranges of the same change twice
+                    let (mut entered_x, mut entered_y) = (false, false);
+
+                    // Any number of further ranges might touch this group
+                    loop {
+                        let (cur_x, cur_y) = (x.get(i_x), y.get(i_y));
+                        let in_x = cur_x.filter(|cx| pos >= cx.from_b);
+                        let in_y = cur_y.filter(|cy| pos >= cy.from_a);
+                        if in_x.is_none() && in_y.is_none() {
+                            break;
+                        }
+                        let next_x = cur_x.map_or(usize::MAX, |cx| match in_x {
+                            Some(_) => cx.to_b,
+                            None => cx.from_b,
+                        });
+                        let next_y = cur_y.map_or(usize::MAX, |cy| match in_y {
+                            Some(_) => cy.to_a,
+                            None => cy.from_a,
+                        });
+                        let next = usize::min(next_x, next_y);
+
+                        if let Some(cx) = in_x {
+                            if pos == cx.from_b && !entered_x {
+                                deleted = Span::join(deleted, cx.deleted.clone(), &combine);
+                                to_a += cx.len_a();
+                                entered_x = true;
+                            }
+                            if in_y.is_none() {
+                                let part =
+                                    Span::slice(&cx.inserted, pos - cx.from_b, next - cx.from_b);
+                                inserted = Span::join(inserted, part, &combine);
+                                to_b += next - pos;
+                            }
+                        }
+                        if let Some(cy) = in_y {
+                            if pos == cy.from_a && !entered_y {
+                                inserted = Span::join(inserted, cy.inserted.clone(), &combine);
+                                to_b += cy.len_b();
+                                entered_y = true;
+                            }
+                            if in_x.is_none() {
+                                let part =
+                                    Span::slice(&cy.deleted, pos - cy.from_a, next - cy.from_a);
+                                deleted = Span::join(deleted, part, &combine);
+                                to_a += next - pos;
+                            }
+                        }
+
+                        if matches!(in_x, Some(cx) if next == cx.to_b) {
+                            i_x += 1;
+                            entered_x = false;
+                        }
+                        if matches!(in_y, Some(cy) if next == cy.to_a) {
+                            i_y += 1;
+                            entered_y = false;
+                        }
+                        pos = next;
+                    }
+
+                    if from_a < to_a || from_b < to_b {
+                        result.push(Change {
+                            from_a,
+                            to_a,
+                            from_b,
+                            to_b,
+                            deleted,
+                            inserted,
+                        });
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use super::Change;
+use crate::model::{Fragment, Node, Schema};
+
+/// A token in the flattened representation of a fragment.
+///
+/// There is exactly one token per position, so token indices can be used as
+/// position offsets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token<T> {
+    /// A UTF-16 code unit of some text
+    Char(u16),
+    /// A leaf node
+    Leaf(T),
+    /// The start of a non-leaf node
+    Open(T),
+    /// The end of a non-leaf node
+    Close,
+}
+
+fn tokens<S: Schema>(
+    fragment: &Fragment<S>,
+    start: usize,
+    end: usize,
+    target: &mut Vec<Token<S::NodeType>>,
+) {
+    let mut off = 0;
+    for child in fragment.children() {
+        let end_off = off + child.node_size();
+        let (from, to) = (usize::max(off, start), usize::min(end_off, end));
+        if from < to {
+            if let Some(text) = child.text_node() {
+                let units = text.text.as_str().encode_utf16();
+                target.extend(units.skip(from - off).take(to - from).map(Token::Char));
+            } else if child.is_leaf() {
+                target.push(Token::Leaf(child.r#type()));
+            } else {
+                if from == off {
+                    target.push(Token::Open(child.r#type()));
+                }
+                let content = child.content().unwrap_or(Fragment::EMPTY_REF);
+                let inner_from = usize::max(off + 1, from) - off - 1;
+                let inner_to = usize::min(end_off - 1, to) - off - 1;
+                tokens(content, inner_from, inner_to, target);
+                if to == end_off {
+                    target.push(Token::Close);
+                }
+            }
+        }
+        off = end_off;
+    }
+}
+
+/// The maximum amount of edits considered by the diff algorithm
+const MAX_DIFF_SIZE: usize = 5000;
+
+/// The minimum amount of unchanged tokens that separate two changes
+fn min_unchanged(size_a: usize, size_b: usize) -> usize {
+    usize::min(15, usize::max(2, usize::max(size_a, size_b) / 10))
+}
+
+/// Collects changed token ranges from back to front, merging the ones
+/// that are less than `min_span` tokens apart.
+struct DiffBuilder<'a, D> {
+    range: &'a Change<D>,
+    min_span: usize,
+    current: Option<(usize, usize, usize, usize)>,
+    result: Vec<Change<D>>,
+}
+
+impl<'a, D: Clone> DiffBuilder<'a, D> {
+    fn add(&mut self, f_a: usize, t_a: usize, f_b: usize, t_b: usize) {
+        match &mut self.current {
+            Some((from_a, _, from_b, _)) if *from_a < t_a + self.min_span => {
+                *from_a = f_a;
+                *from_b = f_b;
+            }
+            current => {
+                if let Some((from_a, to_a, from_b, to_b)) = current.take() {
+                    self.result
+                        .push(self.range.slice(from_a, to_a, from_b, to_b));
+                }
+                *current = Some((f_a, t_a, f_b, t_b));
+            }
+        }
+    }
+
+    fn finish(mut self) -> Vec<Change<D>> {
+        if let Some((from_a, to_a, from_b, to_b)) = self.current {
+            self.result
+                .push(self.range.slice(from_a, to_a, from_b, to_b));
+        }
+        self.result.reverse();
+        self.result
+    }
+}
+
+/// Compute the changes between the parts of `fragment_a` and `fragment_b`
+/// covered by `range`, at the granularity of single characters and node
+/// boundaries. Returns an empty list if the content is the same.
+pub(crate) fn compute_diff<S: Schema, D: Clone>(
+    fragment_a: &Fragment<S>,
+    fragment_b: &Fragment<S>,
+    range: &Change<D>,
+) -> Vec<Change<D>> {
+    let mut tok_a = Vec::new();
+    let mut tok_b = Vec::new();
+    tokens(fragment_a, range.from_a, range.to_a, &mut tok_a);
+    tokens(fragment_b, range.from_b, range.to_b, &mut tok_b);
+
+    // Scan from both sides to cheaply eliminate work
+    let mut start = 0;
+    while start < tok_a.len() && start < tok_b.len() && tok_a[start] == tok_b[start] {
+        start += 1;
+    }
+    if start == tok_a.len() && start == tok_b.len() {
+        return Vec::new();
+    }
+    let (mut end_a, mut end_b) = (tok_a.len(), tok_b.len());
+    while end_a > start && end_b > start && tok_a[end_a - 1] == tok_b[end_b - 1] {
+        end_a -= 1;
+        end_b -= 1;
+    }
+    // If the result is simple, return the remaining region as the diff
+    if end_a == start || end_b == start || (end_a == end_b && end_a == start + 1) {
+        return vec![range.slice(start, end_a, start, end_b)];
+    }
+
+    // This is an implementation of Myers' diff algorithm, see
+    // https://neil.fraser.name/writing/diff/myers.pdf
+    let (a, b) = (&tok_a[start..end_a], &tok_b[start..end_b]);
+    let (len_a, len_b) = (a.len() as isize, b.len() as isize);
+    let max = usize::min(MAX_DIFF_SIZE, a.len() + b.len()) as isize;
+    let off = max + 1;
+    let mut frontier = vec![0isize; 2 * off as usize + 1];
+    // For each edit distance, the part of the frontier it started from
+    let mut history: Vec<Vec<isize>> = Vec::new();
+
+    for size in 0..=max {
+        let window = (off - size - 1) as usize..=(off + size + 1) as usize;
+        history.push(frontier[window].to_vec());
+        for diag in (-size..=size).step_by(2) {
+            let next = frontier[(diag + 1 + off) as usize];
+            let prev = frontier[(diag - 1 + off) as usize];
+            let mut x = if diag == -size || (diag != size && prev < next) {
+                next
+            } else {
+                prev + 1
+            };
+            let mut y = x - diag;
+            while x < len_a && y < len_b && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            frontier[(diag + off) as usize] = x;
+
+            if x >= len_a && y >= len_b {
+                // Trace back through the history to build up the changed ranges
+                let mut builder = DiffBuilder {
+                    range,
+                    min_span: min_unchanged(a.len(), b.len()),
+                    current: None,
+                    result: Vec::new(),
+                };
+                let mut diag = diag;
+                for size in (1..=size).rev() {
+                    let frontier = &history[size as usize];
+                    let at = |d: isize| frontier[(d + size + 1) as usize];
+                    let (next, prev) = (at(diag + 1), at(diag - 1));
+                    let pos =
+                        |x: isize, diag: isize| (start + x as usize, start + (x - diag) as usize);
+                    if diag == -size || (diag != size && prev < next) {
+                        // Insertion
+                        diag += 1;
+                        let (x, y) = pos(next, diag);
+                        builder.add(x, x, y, y + 1);
+                    } else {
+                        // Deletion
+                        diag -= 1;
+                        let (x, y) = pos(prev, diag);
+                        builder.add(x, x + 1, y, y);
+                    }
+                }
+                return builder.finish();
+            }
+        }
+    }
+
+    // The maximum amount of work was done, return a change spanning the entire range
+    vec![range.slice(start, end_a, start, end_b)]
+}
@@ -0,0 +1,310 @@
+//! # Change tracking
+//!
+//! This module is a port of `prosemirror-changeset`. A [`ChangeSet`] accumulates the
+//! ranges that were inserted and deleted by a sequence of steps, with metadata (e.g. the
+//! author or a timestamp) attached to each piece. This can be used to compute "blame" or
+//! suggested-changes views from a log of steps.
+mod change;
+mod diff;
+mod simplify;
+
+pub use change::{Change, Span};
+pub use simplify::simplify_changes;
+
+use crate::model::{Fragment, Node, Schema};
+use crate::transform::StepMap;
+use derivative::Derivative;
+use diff::compute_diff;
+
+/// A function that combines the data of two adjacent spans, or returns `None`
+/// if they should stay separate.
+pub type Combine<D> = fn(&D, &D) -> Option<D>;
+
+/// A change set tracks the changes to a document from a given point in the past.
+///
+/// It condenses a number of step maps down to a flat sequence of replacements, and
+/// simplifies replacements that partially undo themselves by comparing their content.
+#[derive(Derivative)]
+#[derivative(Debug(bound = "D: std::fmt::Debug"), Clone(bound = "D: Clone"))]
+pub struct ChangeSet<S: Schema, D> {
+    doc: S::Node,
+    #[derivative(Debug = "ignore")]
+    combine: Combine<D>,
+    changes: Vec<Change<D>>,
+}
+
+impl<S: Schema, D: Clone + PartialEq> ChangeSet<S, D> {
+    /// Create a change set with the given base document. Adjacent spans are
+    /// joined when their data is equal.
+    pub fn new(doc: S::Node) -> Self {
+        Self::with_combine(doc, |a, b| if a == b { Some(a.clone()) } else { None })
+    }
+}
+
+impl<S: Schema, D: Clone> ChangeSet<S, D> {
+    /// Create a change set with the given base document and a function that
+    /// combines the data of adjacent spans.
+    pub fn with_combine(doc: S::Node, combine: Combine<D>) -> Self {
+        ChangeSet {
+            doc,
+            combine,
+            changes: Vec::new(),
+        }
+    }
+
+    /// The starting document of the change set.
+    pub fn start_doc(&self) -> &S::Node {
+        &self.doc
+    }
+
+    /// The replaced ranges, ordered by position.
+    pub fn changes(&self) -> &[Change<D>] {
+        &self.changes
+    }
+
+    /// Computes a new change set by adding the given step maps, each with its
+    /// metadata. `new_doc` is the current document after all of these steps.
+    pub fn add_steps<'m, I>(self, new_doc: &S::Node, maps: I) -> Self
+    where
+        I: IntoIterator<Item = (&'m StepMap, D)>,
+    {
+        let mut step_changes = Vec::new();
+        for (map, data) in maps {
+            // Each range is applied after the previous ones of the same map
+            let mut off: isize = 0;
+            map.for_each(|from_a, to_a, from_b, to_b| {
+                let span = |from: usize, to: usize| match from == to {
+                    true => Vec::new(),
+                    false => vec![Span::new(to - from, data.clone())],
+                };
+                step_changes.push(Change {
+                    from_a: (from_a as isize + off) as usize,
+                    to_a: (to_a as isize + off) as usize,
+                    from_b,
+                    to_b,
+                    deleted: span(from_a, to_a),
+                    inserted: span(from_b, to_b),
+                });
+                off += (to_b - from_b) as isize - (to_a - from_a) as isize;
+            });
+        }
+        if step_changes.is_empty() {
+            return self;
+        }
+
+        let new_changes = merge_all(&step_changes, self.combine);
+        let mut changes = Change::merge(&self.changes, &new_changes, self.combine);
+
+        // Minimize the changes that touch the new ranges by diffing their content
+        let old_content = self.doc.content().unwrap_or(Fragment::EMPTY_REF);
+        let new_content = new_doc.content().unwrap_or(Fragment::EMPTY_REF);
+        let mut i = 0;
+        while i < changes.len() {
+            let change = &changes[i];
+            if change.from_a == change.to_a
+                || change.from_b == change.to_b
+                || !new_changes
+                    .iter()
+                    .any(|r| r.to_b > change.from_b && r.from_b < change.to_b)
+            {
+                i += 1;
+                continue;
+            }
+            let diff = compute_diff(old_content, new_content, change);
+            // If they are completely different, there is nothing to do
+            if let [single] = &diff[..] {
+                if single.from_b == change.from_b && single.to_b == change.to_b {
+                    i += 1;
+                    continue;
+                }
+            }
+            let len = diff.len();
+            changes.splice(i..=i, diff);
+            i += len;
+        }
+
+        ChangeSet { changes, ..self }
+    }
+}
+
+fn merge_all<D: Clone>(ranges: &[Change<D>], combine: Combine<D>) -> Vec<Change<D>> {
+    if ranges.len() == 1 {
+        return ranges.to_vec();
+    }
+    let (left, right) = ranges.split_at(ranges.len() / 2);
+    Change::merge(
+        &merge_all(left, combine),
+        &merge_all(right, combine),
+        combine,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{simplify_changes, Change, ChangeSet, Span};
+    use crate::markdown::{
+        helper::{doc, em, node, p},
+        MarkdownMark, MarkdownNode, MD,
+    };
+    use crate::model::{Fragment, Node, Slice};
+    use crate::transform::{AddMarkStep, ReplaceStep, Span as StepSpan, Step, StepMap};
+
+    fn replace(from: usize, to: usize, text: &str) -> Step<MD> {
+        let content = match text {
+            "" => Fragment::new(),
+            _ => Fragment::from((MarkdownNode::text(text),)),
+        };
+        Step::Replace(ReplaceStep {
+            span: StepSpan { from, to },
+            slice: Slice::new(content, 0, 0),
+            structure: false,
+        })
+    }
+
+    /// Apply the steps to `current`, each tagged with its data, and track them in a change set
+    fn track(
+        set: ChangeSet<MD, &'static str>,
+        current: Option<MarkdownNode>,
+        steps: &[(Step<MD>, &'static str)],
+    ) -> (ChangeSet<MD, &'static str>, MarkdownNode) {
+        let mut current = current.unwrap_or_else(|| set.start_doc().clone());
+        let mut maps: Vec<(StepMap, &str)> = Vec::new();
+        for (step, data) in steps {
+            current = step.apply(&current).unwrap();
+            maps.push((step.get_map(), data));
+        }
+        let set = set.add_steps(&current, maps.iter().map(|(map, data)| (map, *data)));
+        (set, current)
+    }
+
+    fn change(
+        a: (usize, usize),
+        b: (usize, usize),
+        deleted: &[(usize, &'static str)],
+        inserted: &[(usize, &'static str)],
+    ) -> Change<&'static str> {
+        let spans = |s: &[(usize, &'static str)]| s.iter().map(|&(l, d)| Span::new(l, d)).collect();
+        Change {
+            from_a: a.0,
+            to_a: a.1,
+            from_b: b.0,
+            to_b: b.1,
+            deleted: spans(deleted),
+            inserted: spans(inserted),
+        }
+    }
+
+    #[test]
+    fn test_step_map() {
+        let map = replace(2, 4, "xyz").get_map();
+        assert_eq!(map.map(1, 1), 1);
+        assert_eq!(map.map(2, -1), 2);
+        assert_eq!(map.map(3, 1), 5);
+        assert_eq!(map.map(4, 1), 5);
+        assert_eq!(map.map(10, 1), 11);
+        assert_eq!(map.invert().map(5, 1), 4);
+
+        let mut ranges = Vec::new();
+        map.for_each(|a, b, c, d| ranges.push((a, b, c, d)));
+        assert_eq!(ranges, vec![(2, 4, 2, 5)]);
+
+        let mark = Step::AddMark(AddMarkStep::<MD> {
+            span: StepSpan { from: 1, to: 3 },
+            mark: MarkdownMark::Em,
+        });
+        assert_eq!(mark.get_map(), StepMap::EMPTY);
+    }
+
+    #[test]
+    fn test_insert_and_delete() {
+        let set = ChangeSet::new(doc(p("hello")));
+        let (set, _) = track(
+            set,
+            None,
+            &[(replace(6, 6, "!"), "a"), (replace(1, 2, ""), "b")],
+        );
+        assert_eq!(
+            set.changes(),
+            &[
+                change((1, 2), (1, 1), &[(1, "b")], &[]),
+                change((6, 6), (5, 6), &[], &[(1, "a")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_adjacent() {
+        let set = ChangeSet::new(doc(p("x")));
+        let steps = [
+            (replace(2, 2, "ab"), "a"),
+            (replace(4, 4, "cd"), "a"),
+            (replace(6, 6, "ef"), "b"),
+        ];
+        let (set, current) = track(set, None, &steps);
+        assert_eq!(
+            set.changes(),
+            &[change((2, 2), (2, 8), &[], &[(4, "a"), (2, "b")])]
+        );
+
+        // Deleting inserted content removes it from the change
+        let (set, _) = track(set, Some(current), &[(replace(3, 7, ""), "c")]);
+        assert_eq!(
+            set.changes(),
+            &[change((2, 2), (2, 4), &[], &[(1, "a"), (1, "b")])]
+        );
+    }
+
+    #[test]
+    fn test_minimize_changes() {
+        // Replacing a word with a similar one only marks the changed characters
+        let set = ChangeSet::new(doc(p("one two three")));
+        let (set, _) = track(set, None, &[(replace(5, 8, "too"), "a")]);
+        assert_eq!(
+            set.changes(),
+            &[change((6, 7), (6, 7), &[(1, "a")], &[(1, "a")])]
+        );
+
+        // Retyping the same text leaves no change
+        let set = ChangeSet::new(doc(p("same")));
+        let (set, _) = track(set, None, &[(replace(1, 5, "same"), "a")]);
+        assert!(set.changes().is_empty());
+
+        // Mark steps don't produce changes
+        let set = ChangeSet::new(doc(p("text")));
+        let mark = Step::AddMark(AddMarkStep {
+            span: StepSpan { from: 1, to: 5 },
+            mark: MarkdownMark::Em,
+        });
+        let (set, current) = track(set, None, &[(mark, "a")]);
+        assert_eq!(current, doc(p(em("text"))));
+        assert!(set.changes().is_empty());
+    }
+
+    #[test]
+    fn test_simplify_changes() {
+        let set = ChangeSet::new(doc(p("the cat sat")));
+        let (set, current) = track(set, None, &[(replace(5, 8, "dog"), "a")]);
+        assert_eq!(current, doc(p(node("the dog sat"))));
+        assert_eq!(set.changes().len(), 1);
+
+        // Partial changes inside a word are expanded to the whole word
+        let set = ChangeSet::new(doc(p("the abcdef sat")));
+        let (set, current) = track(set, None, &[(replace(5, 11, "xbcdey"), "a")]);
+        assert_eq!(
+            set.changes(),
+            &[
+                change((5, 6), (5, 6), &[(1, "a")], &[(1, "a")]),
+                change((10, 11), (10, 11), &[(1, "a")], &[(1, "a")]),
+            ]
+        );
+        let simple = simplify_changes::<MD, _>(set.changes(), &current);
+        let spans = [(1, "a"), (4, "a"), (1, "a")];
+        assert_eq!(simple, vec![change((5, 11), (5, 11), &spans, &spans)]);
+
+        // Single character replacements are kept
+        let set = ChangeSet::new(doc(p("cat")));
+        let (set, current) = track(set, None, &[(replace(2, 3, "u"), "a")]);
+        let simple = simplify_changes::<MD, _>(set.changes(), &current);
+        assert_eq!(simple, set.changes());
+    }
+}
@@ -0,0 +1,192 @@
+use super::{Change, Span};
+use crate::model::{Fragment, Node, Schema};
+
+/// How far around the changes to look for word boundaries
+const MAX_SIMPLIFY_DISTANCE: usize = 30;
+
+fn is_letter(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Whether the token at each position in `from..to` is a letter
+fn letters<S: Schema>(fragment: &Fragment<S>, from: usize, to: usize, target: &mut Vec<bool>) {
+    let mut pos = 0;
+    for child in fragment.children() {
+        let end = pos + child.node_size();
+        if end > from && pos < to {
+            if let Some(text) = child.text_node() {
+                let units = text
+                    .text
+                    .as_str()
+                    .chars()
+                    .flat_map(|c| (0..c.len_utf16()).map(move |_| is_letter(c)));
+                let skip = from.saturating_sub(pos);
+                target.extend(units.skip(skip).take(usize::min(end, to) - pos - skip));
+            } else if child.is_leaf() {
+                target.push(false);
+            } else {
+                if pos >= from {
+                    target.push(false);
+                }
+                let content = child.content().unwrap_or(Fragment::EMPTY_REF);
+                letters(content, from.saturating_sub(pos + 1), to - pos - 1, target);
+                if end <= to {
+                    target.push(false);
+                }
+            }
+        }
+        pos = end;
+    }
+}
+
+/// Simplifies a set of changes for presentation.
+///
+/// Having both insertions and deletions within a word is confusing, so when such
+/// changes occur without a word boundary between them, they are expanded to cover
+/// the entire words (in the new document) they touch. Single-character replacements
+/// are kept as they are.
+pub fn simplify_changes<S: Schema, D: Clone>(
+    changes: &[Change<D>],
+    doc: &S::Node,
+) -> Vec<Change<D>> {
+    let mut result = Vec::new();
+    let mut i = 0;
+    while i < changes.len() {
+        let start = i;
+        let mut end = changes[i].to_b;
+        while i + 1 < changes.len() && changes[i + 1].from_b <= end + MAX_SIMPLIFY_DISTANCE {
+            i += 1;
+            end = changes[i].to_b;
+        }
+        simplify_adjacent_changes::<S, D>(&changes[start..=i], doc, &mut result);
+        i += 1;
+    }
+    result
+}
+
+fn simplify_adjacent_changes<S: Schema, D: Clone>(
+    changes: &[Change<D>],
+    doc: &S::Node,
+    target: &mut Vec<Change<D>>,
+) {
+    let content = doc.content().unwrap_or(Fragment::EMPTY_REF);
+    let start = changes[0].from_b.saturating_sub(MAX_SIMPLIFY_DISTANCE);
+    let end = usize::min(
+        content.size(),
+        changes[changes.len() - 1].to_b + MAX_SIMPLIFY_DISTANCE,
+    );
+    let mut text = Vec::new();
+    letters(content, start, end, &mut text);
+    let letter_at = |pos: usize| pos < end && text[pos - start];
+
+    let mut i = 0;
+    while i < changes.len() {
+        let start_i = i;
+        let mut last = &changes[i];
+        let (mut deleted, mut inserted) = (last.len_a(), last.len_b());
+        while i + 1 < changes.len() {
+            let next = &changes[i + 1];
+            let mut boundary = false;
+            let mut prev_letter = last.to_b > start && letter_at(last.to_b - 1);
+            for pos in last.to_b..next.from_b {
+                let next_letter = letter_at(pos);
+                if prev_letter != next_letter {
+                    boundary = true;
+                    break;
+                }
+                prev_letter = next_letter;
+            }
+            if boundary {
+                break;
+            }
+            deleted += next.len_a();
+            inserted += next.len_b();
+            last = next;
+            i += 1;
+        }
+
+        if inserted > 0 && deleted > 0 && !(inserted == 1 && deleted == 1) {
+            let mut from = changes[start_i].from_b;
+            let mut to = changes[i].to_b;
+            if letter_at(from) {
+                while from > start && letter_at(from - 1) {
+                    from -= 1;
+                }
+            }
+            if to > start && letter_at(to - 1) {
+                while letter_at(to) {
+                    to += 1;
+                }
+            }
+            let joined = fill_change(&changes[start_i..=i], from, to);
+            match target.last_mut() {
+                Some(last) if last.to_a == joined.from_a => {
+                    last.to_a = joined.to_a;
+                    last.to_b = joined.to_b;
+                    last.deleted.extend(joined.deleted);
+                    last.inserted.extend(joined.inserted);
+                }
+                _ => target.push(joined),
+            }
+        } else {
+            target.extend_from_slice(&changes[start_i..=i]);
+        }
+        i += 1;
+    }
+}
+
+/// Join the given changes into one covering `from_b..to_b`, attributing the
+/// unchanged content in between to the data of the surrounding spans.
+fn fill_change<D: Clone>(changes: &[Change<D>], from_b: usize, to_b: usize) -> Change<D> {
+    let first = &changes[0];
+    let last = &changes[changes.len() - 1];
+    let from_a = first.from_a - (first.from_b - from_b);
+    let to_a = last.to_a + (to_b - last.to_b);
+
+    let first_data = |spans: &[Span<D>], or: &[Span<D>]| {
+        spans
+            .first()
+            .or_else(|| or.first())
+            .map(|span| span.data.clone())
+    };
+    let mut del_data = first_data(&first.deleted, &first.inserted);
+    let mut ins_data = first_data(&first.inserted, &first.deleted);
+
+    let mut deleted = Vec::new();
+    let mut inserted = Vec::new();
+    let (mut pos_a, mut pos_b) = (from_a, from_b);
+    for i in 0..=changes.len() {
+        let next = changes.get(i);
+        let end_a = next.map_or(to_a, |next| next.from_a);
+        let end_b = next.map_or(to_b, |next| next.from_b);
+        if let (true, Some(data)) = (end_a > pos_a, &del_data) {
+            deleted.push(Span::new(end_a - pos_a, data.clone()));
+        }
+        if let (true, Some(data)) = (end_b > pos_b, &ins_data) {
+            inserted.push(Span::new(end_b - pos_b, data.clone()));
+        }
+        let next = match next {
+            Some(next) => next,
+            None => break,
+        };
+        deleted.extend_from_slice(&next.deleted);
+        inserted.extend_from_slice(&next.inserted);
+        if let Some(span) = deleted.last() {
+            del_data = Some(span.data.clone());
+        }
+        if let Some(span) = inserted.last() {
+            ins_data = Some(span.data.clone());
+        }
+        pos_a = next.to_a;
+        pos_b = next.to_b;
+    }
+
+    Change {
+        from_a,
+        to_a,
+        from_b,
+        to_b,
+        deleted,
+        inserted,
+    }
+}
@@ -1,4 +1,5 @@
-use serde::de::{Deserialize, Deserializer};
+use crate::model::AttrSpec;
+use serde::de::{Deserialize, Deserializer, Error};
 
 pub fn deserialize_or_default<'de, T, D>(deserializer: D) -> Result<T, D::Error>
 where
@@ -8,3 +9,13 @@ where
     let opt_string: Option<T> = Deserialize::deserialize(deserializer)?;
     Ok(opt_string.unwrap_or_default())
 }
+
+pub fn deserialize_attrs<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + AttrSpec,
+{
+    let attrs = T::deserialize(deserializer)?;
+    attrs.validate().map_err(D::Error::custom)?;
+    Ok(attrs)
+}
@@ -8,8 +8,10 @@
 #[macro_use]
 extern crate derive_new;
 
+pub mod changeset;
 pub(crate) mod de;
 pub mod markdown;
 pub mod model;
+pub(crate) mod ser;
 pub mod transform;
 pub mod util;
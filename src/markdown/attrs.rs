@@ -1,29 +1,56 @@
-use crate::de;
+use crate::model::{AttrError, AttrSpec};
+use crate::{de, ser};
 use serde::{Deserialize, Serialize};
 
 /// Attributes for a heading (i.e. `<h1>`, `<h2>`, ...)
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
 pub struct HeadingAttrs {
     /// The level of the heading (i.e. `1` for `<h1>`)
     pub level: u8,
 }
 
+impl Default for HeadingAttrs {
+    fn default() -> Self {
+        HeadingAttrs { level: 1 }
+    }
+}
+
+impl AttrSpec for HeadingAttrs {
+    fn validate(&self) -> Result<(), AttrError> {
+        AttrError::check_range("level", self.level, 1, 6)
+    }
+}
+
 /// Attributes for a code block
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
 pub struct CodeBlockAttrs {
     /// ???
+    #[serde(deserialize_with = "de::deserialize_or_default")]
     pub params: String,
 }
 
+impl AttrSpec for CodeBlockAttrs {
+    fn validate(&self) -> Result<(), AttrError> {
+        // The params are written on the line of the opening fence
+        AttrError::check_allowed("params", &self.params, |p| !p.contains('\n'))
+    }
+}
+
 /// Attributes for a bullet list
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
 pub struct BulletListAttrs {
     /// ???
     pub tight: bool,
 }
 
+impl AttrSpec for BulletListAttrs {}
+
 /// Attributes for an ordered list
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
 pub struct OrderedListAttrs {
     /// Initial value
     pub order: usize,
@@ -31,25 +58,110 @@ pub struct OrderedListAttrs {
     pub tight: bool,
 }
 
+impl Default for OrderedListAttrs {
+    fn default() -> Self {
+        OrderedListAttrs {
+            order: 1,
+            tight: false,
+        }
+    }
+}
+
+impl AttrSpec for OrderedListAttrs {}
+
+/// Attributes for a task list item
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct TaskItemAttrs {
+    /// Whether the task is done, i.e. `- [x]`
+    pub checked: bool,
+}
+
+impl AttrSpec for TaskItemAttrs {}
+
+/// Attributes for a footnote definition or reference
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct FootnoteAttrs {
+    /// The label, i.e. `1` for `[^1]`
+    pub label: String,
+}
+
+impl AttrSpec for FootnoteAttrs {
+    fn validate(&self) -> Result<(), AttrError> {
+        AttrError::check_allowed("label", &self.label, |l| {
+            !l.is_empty() && !l.contains([']', '\n'])
+        })
+    }
+}
+
+/// Attributes for raw HTML
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct HtmlAttrs {
+    /// The HTML source, written out unchanged
+    pub html: String,
+}
+
+impl AttrSpec for HtmlAttrs {}
+
 /// Attributes for an image
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
 pub struct ImageAttrs {
     /// Source URL
     pub src: String,
-    #[serde(default, deserialize_with = "de::deserialize_or_default")]
     /// Alternative Text (Accessibility)
+    #[serde(
+        default,
+        deserialize_with = "de::deserialize_or_default",
+        serialize_with = "ser::serialize_empty_as_null"
+    )]
     pub alt: String,
     /// Title (Tooltip)
-    #[serde(default, deserialize_with = "de::deserialize_or_default")]
+    #[serde(
+        default,
+        deserialize_with = "de::deserialize_or_default",
+        serialize_with = "ser::serialize_empty_as_null"
+    )]
     pub title: String,
 }
 
+impl AttrSpec for ImageAttrs {}
+
 /// The attributes for a hyperlink
-#[derive(Debug, Hash, Eq, Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Default, Hash, Eq, Clone, PartialEq, Deserialize, Serialize)]
 pub struct LinkAttrs {
     /// The URL the link points to
     pub href: String,
     /// The title of the link
-    #[serde(default, deserialize_with = "de::deserialize_or_default")]
+    #[serde(
+        default,
+        deserialize_with = "de::deserialize_or_default",
+        serialize_with = "ser::serialize_empty_as_null"
+    )]
     pub title: String,
 }
+
+impl AttrSpec for LinkAttrs {}
+
+/// The alignment of a table column
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Alignment {
+    /// Left aligned, i.e. `:--`
+    Left,
+    /// Centered, i.e. `:-:`
+    Center,
+    /// Right aligned, i.e. `--:`
+    Right,
+}
+
+/// Attributes for a table cell or header
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct TableCellAttrs {
+    /// The alignment of the column, if any
+    pub align: Option<Alignment>,
+}
+
+impl AttrSpec for TableCellAttrs {}
@@ -16,12 +16,18 @@ pub enum MarkdownContentMatch {
     OrTextImageStar,
     /// `text*`
     TextStar,
-    /// `list_item+`
+    /// `(list_item | task_item)+`
     ListItemPlus,
-    /// `list_item*`
+    /// `(list_item | task_item)*`
     ListItemStar,
     /// `paragraph block*`
     ParagraphBlockStar,
+    /// `table_row+`
+    TableRowPlus,
+    /// `table_row*`
+    TableRowStar,
+    /// `(table_cell | table_header)*`
+    TableCellStar,
     /// empty
     Empty,
 }
@@ -37,13 +43,27 @@ impl ContentMatch<MD> for MarkdownContentMatch {
             ),
             Self::TextStar => then_some(matches!(r#type, MarkdownNodeType::Text), Self::TextStar),
             Self::ListItemPlus | Self::ListItemStar => then_some(
-                matches!(r#type, MarkdownNodeType::ListItem),
+                matches!(
+                    r#type,
+                    MarkdownNodeType::ListItem | MarkdownNodeType::TaskItem
+                ),
                 Self::ListItemStar,
             ),
             Self::ParagraphBlockStar => then_some(
                 matches!(r#type, MarkdownNodeType::Paragraph),
                 Self::BlockStar,
             ),
+            Self::TableRowPlus | Self::TableRowStar => then_some(
+                matches!(r#type, MarkdownNodeType::TableRow),
+                Self::TableRowStar,
+            ),
+            Self::TableCellStar => then_some(
+                matches!(
+                    r#type,
+                    MarkdownNodeType::TableCell | MarkdownNodeType::TableHeader
+                ),
+                Self::TableCellStar,
+            ),
             Self::Empty => None,
         }
     }
@@ -78,6 +98,8 @@ impl ContentMatch<MD> for MarkdownContentMatch {
                 | Self::OrTextImageStar
                 | Self::TextStar
                 | Self::ListItemStar
+                | Self::TableRowStar
+                | Self::TableCellStar
                 | Self::Empty
         )
     }
@@ -106,6 +128,10 @@ impl MarkdownContentMatch {
                 matches!(other, Self::ListItemPlus | Self::ListItemStar)
             }
             Self::ParagraphBlockStar => matches!(other, Self::BlockPlus | Self::ParagraphBlockStar),
+            Self::TableRowPlus | Self::TableRowStar => {
+                matches!(other, Self::TableRowPlus | Self::TableRowStar)
+            }
+            Self::TableCellStar => other == Self::TableCellStar,
             Self::Empty => false,
         }
     }
@@ -0,0 +1,49 @@
+//! Checking that the footnotes of a document are complete.
+use super::MarkdownNode;
+use crate::model::{AttrNode, Leaf, Node};
+use std::collections::HashSet;
+
+/// The labels of the footnotes that are referenced but not defined in the document, in the
+/// order of their first reference
+pub fn undefined_footnotes(doc: &MarkdownNode) -> Vec<&str> {
+    let mut defined = HashSet::new();
+    let mut referenced = Vec::new();
+    for (node, ..) in doc.descendants() {
+        match node {
+            MarkdownNode::FootnoteDefinition(AttrNode { attrs, .. }) => {
+                defined.insert(attrs.label.as_str());
+            }
+            MarkdownNode::FootnoteReference(Leaf { attrs })
+                if !referenced.contains(&attrs.label.as_str()) =>
+            {
+                referenced.push(attrs.label.as_str());
+            }
+            _ => {}
+        }
+    }
+    referenced.retain(|label| !defined.contains(label));
+    referenced
+}
+
+#[cfg(test)]
+mod tests {
+    use super::undefined_footnotes;
+    use crate::markdown::helper::{doc, fnref, footnote, p};
+
+    #[test]
+    fn test_undefined_footnotes() {
+        let d = doc!(
+            p!(
+                "a",
+                fnref!(label = "1"),
+                "b",
+                fnref!(label = "x"),
+                fnref!(label = "1")
+            ),
+            footnote!(label = "1"; p!("Note")),
+            p!(fnref!(label = "y"))
+        );
+        assert_eq!(undefined_footnotes(&d), vec!["x", "y"]);
+        assert!(undefined_footnotes(&doc!(p!("a"))).is_empty());
+    }
+}
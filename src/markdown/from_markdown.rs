@@ -1,13 +1,15 @@
 use super::{
-    BulletListAttrs, CodeBlockAttrs, HeadingAttrs, ImageAttrs, LinkAttrs, MarkdownMark,
-    MarkdownNode, OrderedListAttrs, MD,
+    Alignment, BulletListAttrs, CodeBlockAttrs, FootnoteAttrs, HeadingAttrs, HtmlAttrs, ImageAttrs,
+    LinkAttrs, MarkdownMark, MarkdownNode, OrderedListAttrs, TableCellAttrs, TaskItemAttrs, MD,
 };
 use crate::model::{AttrNode, Block, Fragment, Leaf, MarkSet, Text, TextNode};
 use displaydoc::Display;
-use pulldown_cmark::{CodeBlockKind, Event, Parser, Tag};
+use pulldown_cmark::{Alignment as CmarkAlignment, CodeBlockKind, Event, Options, Parser, Tag};
 use std::{
+    collections::HashSet,
     convert::{TryFrom, TryInto},
     num::TryFromIntError,
+    ops::Range,
 };
 use thiserror::Error;
 
@@ -24,32 +26,220 @@ pub enum FromMarkdownError {
     MisplacedEndTag(&'static str, Attrs),
     /// No children allowed in {0:?}
     NoChildrenAllowed(&'static str),
+    /// Footnote `{0}` is referenced but not defined
+    UndefinedFootnote(String),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Attrs {
     Doc,
     Paragraph,
+    /// A paragraph in an item of a tight list, which has no tags of its own
+    TightParagraph,
     Heading(HeadingAttrs),
     Blockquote,
     CodeBlock(CodeBlockAttrs),
     OrderedList(OrderedListAttrs),
     BulletList(BulletListAttrs),
     ListItem,
+    TaskItem(TaskItemAttrs),
     Image(ImageAttrs),
+    Table(Vec<Option<Alignment>>),
+    TableHead,
+    TableRow,
+    TableCell(TableCellAttrs),
+    FootnoteDefinition(FootnoteAttrs),
+}
+
+fn alignment(align: CmarkAlignment) -> Option<Alignment> {
+    match align {
+        CmarkAlignment::None => None,
+        CmarkAlignment::Left => Some(Alignment::Left),
+        CmarkAlignment::Center => Some(Alignment::Center),
+        CmarkAlignment::Right => Some(Alignment::Right),
+    }
+}
+
+/// How a soft line break, i.e. a single newline within a paragraph, is imported
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum SoftBreak {
+    /// Replace it with a space, like `prosemirror-markdown` does
+    #[default]
+    Space,
+    /// Keep it as a newline in the text
+    Newline,
+    /// Turn it into a `hard_break` node
+    HardBreak,
+}
+
+/// How a construct is handled that has no place in the document. These are:
+///
+/// - a reference to a footnote that is not defined, with its source `[^label]`
+/// - a task list marker that is not at the start of a list item, with its source `[ ] ` or `[x] `.
+///   The parser doesn't emit these, so it only matters for events from somewhere else.
+///
+/// Everything else in the markdown has a node or mark in the schema. Blocks that would be
+/// invalid without content, like an empty list item or quote, get an empty paragraph instead.
+///
+/// The default is [`PlainText`](Unsupported::PlainText), because GitHub renders a reference to
+/// an undefined footnote as the text it was written as. Use [`Error`](Unsupported::Error) to find
+/// out about such references, e.g. to report them to the author.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Unsupported {
+    /// Fail with an error
+    Error,
+    /// Leave it out of the document
+    Drop,
+    /// Keep its source as text
+    #[default]
+    PlainText,
+}
+
+/// Options for [`from_markdown_with`]
+///
+/// The GFM extensions are enabled by default, smart punctuation is not.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkdownParseOptions {
+    /// How soft line breaks are imported
+    pub soft_break: SoftBreak,
+    /// Whether tables are parsed
+    pub tables: bool,
+    /// Whether `~~strikethrough~~` is parsed
+    pub strikethrough: bool,
+    /// Whether list items starting with `[ ]` or `[x]` are parsed as task items
+    pub tasklists: bool,
+    /// Whether footnotes are parsed
+    pub footnotes: bool,
+    /// Whether quotes, `--`, `---` and `...` are replaced with their typographic characters
+    pub smart_punctuation: bool,
+    /// How unsupported constructs are handled
+    pub unsupported: Unsupported,
+}
+
+impl Default for MarkdownParseOptions {
+    fn default() -> Self {
+        Self {
+            soft_break: SoftBreak::default(),
+            tables: true,
+            strikethrough: true,
+            tasklists: true,
+            footnotes: true,
+            smart_punctuation: false,
+            unsupported: Unsupported::default(),
+        }
+    }
+}
+
+impl MarkdownParseOptions {
+    /// The options of the parser
+    fn extensions(&self) -> Options {
+        let mut extensions = Options::empty();
+        extensions.set(Options::ENABLE_TABLES, self.tables);
+        extensions.set(Options::ENABLE_STRIKETHROUGH, self.strikethrough);
+        extensions.set(Options::ENABLE_TASKLISTS, self.tasklists);
+        extensions.set(Options::ENABLE_FOOTNOTES, self.footnotes);
+        extensions
+    }
 }
 
 /// Creates a MarkdownNode::Doc from a text
 pub fn from_markdown(text: &str) -> Result<MarkdownNode, FromMarkdownError> {
-    let parser = Parser::new(text);
-    let mut d = MarkdownDeserializer::default();
-    d.deserialize(parser)
+    from_markdown_with(text, &MarkdownParseOptions::default())
+}
+
+/// Creates a MarkdownNode::Doc from a text, with the given options
+pub fn from_markdown_with(
+    text: &str,
+    options: &MarkdownParseOptions,
+) -> Result<MarkdownNode, FromMarkdownError> {
+    let extensions = options.extensions();
+    // References can come before the definition of their footnote
+    let footnotes = if options.footnotes {
+        Parser::new_ext(text, extensions)
+            .filter_map(|event| match event {
+                Event::Start(Tag::FootnoteDefinition(label)) => Some(label.to_string()),
+                _ => None,
+            })
+            .collect()
+    } else {
+        HashSet::new()
+    };
+    let mut d = MarkdownDeserializer {
+        options: options.clone(),
+        footnotes,
+        ..MarkdownDeserializer::default()
+    };
+    d.deserialize(text, Parser::new_ext(text, extensions).into_offset_iter())
+}
+
+/// The content of a block node, with an empty paragraph where the schema requires one. Like
+/// `NodeType.createAndFill`, this keeps empty items and quotes, e.g. `-` or `>`, in the document.
+fn fill_blocks(mut content: Vec<MarkdownNode>, paragraph_first: bool) -> Fragment<MD> {
+    let first_ok = match content.first() {
+        Some(MarkdownNode::Paragraph(_)) => true,
+        Some(_) => !paragraph_first,
+        None => false,
+    };
+    if !first_ok {
+        let empty = MarkdownNode::Paragraph(Block {
+            content: Fragment::new(),
+        });
+        content.insert(0, empty);
+    }
+    Fragment::from(content)
+}
+
+/// Replace quotes, dashes and ellipses with their typographic characters. `prev` is the character
+/// before the text, which decides whether a quote opens or closes.
+fn smart_punctuation(text: &str, mut prev: Option<char>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let opens = match prev {
+            Some(p) => p.is_whitespace() || "([{-\u{2013}\u{2014}".contains(p),
+            None => true,
+        };
+        match c {
+            '"' if opens => out.push('\u{201c}'),
+            '"' => out.push('\u{201d}'),
+            '\'' if opens => out.push('\u{2018}'),
+            '\'' => out.push('\u{2019}'),
+            '.' if chars.clone().take(2).eq(['.', '.']) => {
+                chars.nth(1);
+                out.push('\u{2026}');
+            }
+            '-' if chars.peek() == Some(&'-') => {
+                let mut count = 1;
+                while chars.next_if_eq(&'-').is_some() {
+                    count += 1;
+                }
+                // Like the CommonMark reference implementation: as many em dashes as possible,
+                // unless the count is even
+                let (em, en) = match (count % 3, count % 2) {
+                    (0, _) => (count / 3, 0),
+                    (_, 0) => (0, count / 2),
+                    (2, _) => (count / 3, 1),
+                    _ => ((count - 4) / 3, 2),
+                };
+                out.push_str(&"\u{2014}".repeat(em));
+                out.push_str(&"\u{2013}".repeat(en));
+            }
+            c => out.push(c),
+        }
+        prev = out.chars().last();
+    }
+    out
 }
 
 #[derive(Default)]
 pub struct MarkdownDeserializer {
     stack: Vec<(Vec<MarkdownNode>, Attrs)>,
     mark_set: MarkSet<MD>,
+    options: MarkdownParseOptions,
+    /// The labels of the footnotes that are defined
+    footnotes: HashSet<String>,
+    /// The end of the last line of block HTML in the source
+    html_end: usize,
 }
 
 impl MarkdownDeserializer {
@@ -80,9 +270,144 @@ impl MarkdownDeserializer {
         Ok(())
     }
 
-    fn deserialize(&mut self, parser: Parser) -> Result<MarkdownNode, FromMarkdownError> {
+    /// Add text with the current marks, joining it with the previous text node if possible. The
+    /// parser emits empty text after code at the end of a table cell, which is skipped.
+    fn add_text(&mut self, text: &str, marks: MarkSet<MD>) -> Result<(), FromMarkdownError> {
+        let last = self.stack.last_mut().ok_or(FromMarkdownError::StackEmpty)?;
+        if text.is_empty() {
+            return Ok(());
+        }
+        let text = Text::from(text.to_string());
+        match last.0.last_mut() {
+            Some(MarkdownNode::Text(prev)) if prev.marks == marks => {
+                prev.text = prev.text.join(&text);
+            }
+            _ => last.0.push(MarkdownNode::Text(TextNode { text, marks })),
+        }
+        Ok(())
+    }
+
+    /// Add a construct that has no place in the document, following the options
+    fn add_unsupported(
+        &mut self,
+        err: FromMarkdownError,
+        source: &str,
+    ) -> Result<(), FromMarkdownError> {
+        match self.options.unsupported {
+            Unsupported::Error => Err(err),
+            Unsupported::Drop => Ok(()),
+            Unsupported::PlainText => self.add_text(source, self.mark_set.clone()),
+        }
+    }
+
+    /// Add a line of HTML, which continues the previous block of HTML if it is on the next line.
+    /// Only container markers like `>` may come between the lines in the source.
+    fn add_html(
+        &mut self,
+        html: &str,
+        range: Range<usize>,
+        source: &str,
+    ) -> Result<(), FromMarkdownError> {
+        let last = self.stack.last_mut().ok_or(FromMarkdownError::StackEmpty)?;
+        let inline = matches!(
+            last.1,
+            Attrs::Paragraph | Attrs::TightParagraph | Attrs::Heading(_) | Attrs::TableCell(_)
+        );
+        if inline {
+            let attrs = HtmlAttrs {
+                html: html.to_owned(),
+            };
+            last.0.push(MarkdownNode::HtmlInline(Leaf { attrs }));
+            return Ok(());
+        }
+
+        let line = html.strip_suffix('\n').unwrap_or(html);
+        match last.0.last_mut() {
+            Some(MarkdownNode::HtmlBlock(Leaf { attrs }))
+                if matches!(
+                    source.get(self.html_end..range.start),
+                    Some(between) if !between.contains('\n')
+                ) =>
+            {
+                attrs.html.push('\n');
+                attrs.html.push_str(line);
+            }
+            _ => {
+                let attrs = HtmlAttrs {
+                    html: line.to_owned(),
+                };
+                last.0.push(MarkdownNode::HtmlBlock(Leaf { attrs }));
+            }
+        }
+        self.html_end = range.end;
+        Ok(())
+    }
+
+    /// Open a paragraph for inline content directly in a list item, which makes the list tight
+    fn open_tight_paragraph(&mut self) {
+        let len = self.stack.len();
+        if len < 2 || !matches!(self.stack[len - 1].1, Attrs::ListItem | Attrs::TaskItem(_)) {
+            return;
+        }
+        match &mut self.stack[len - 2].1 {
+            Attrs::BulletList(attrs) => attrs.tight = true,
+            Attrs::OrderedList(attrs) => attrs.tight = true,
+            _ => {}
+        }
+        self.push_stack(Attrs::TightParagraph);
+    }
+
+    /// Close the paragraph opened by `open_tight_paragraph`, if any
+    fn close_tight_paragraph(&mut self) -> Result<(), FromMarkdownError> {
+        if let Some((_, Attrs::TightParagraph)) = self.stack.last() {
+            let (content, _) = self.pop_stack()?;
+            let p = MarkdownNode::Paragraph(Block {
+                content: Fragment::from(content),
+            });
+            self.add_content(p)?;
+        }
+        Ok(())
+    }
+
+    /// The alignment of the next cell in the current row
+    fn cell_attrs(&self) -> TableCellAttrs {
+        let mut iter = self.stack.iter().rev();
+        let column = iter.next().map_or(0, |(row, _)| row.len());
+        let align = match iter.next() {
+            Some((_, Attrs::Table(alignments))) => alignments.get(column).copied().flatten(),
+            _ => None,
+        };
+        TableCellAttrs { align }
+    }
+
+    fn deserialize<'a>(
+        &mut self,
+        source: &str,
+        events: impl IntoIterator<Item = (Event<'a>, Range<usize>)>,
+    ) -> Result<MarkdownNode, FromMarkdownError> {
         self.push_stack(Attrs::Doc);
-        for event in parser {
+        for (event, range) in events {
+            // Tight lists contain inline content without paragraph tags
+            match &event {
+                Event::Start(
+                    Tag::Emphasis
+                    | Tag::Strong
+                    | Tag::Strikethrough
+                    | Tag::Link(..)
+                    | Tag::Image(..),
+                )
+                | Event::Text(_)
+                | Event::Code(_)
+                | Event::FootnoteReference(_)
+                | Event::SoftBreak
+                | Event::HardBreak => self.open_tight_paragraph(),
+                // Lines of block HTML end with a line break, inline HTML never does
+                Event::Html(html) if !html.ends_with('\n') => self.open_tight_paragraph(),
+                Event::Start(_) | Event::End(Tag::Item) | Event::Rule | Event::Html(_) => {
+                    self.close_tight_paragraph()?
+                }
+                _ => {}
+            }
             match event {
                 Event::Start(tag) => match tag {
                     Tag::Paragraph => {
@@ -124,20 +449,24 @@ impl MarkdownDeserializer {
                     Tag::Item => {
                         self.stack.push((Vec::new(), Attrs::ListItem));
                     }
-                    Tag::FootnoteDefinition(_) => {
-                        return Err(FromMarkdownError::NotSupported("FootnoteDefinition"));
+                    Tag::FootnoteDefinition(label) => {
+                        self.push_stack(Attrs::FootnoteDefinition(FootnoteAttrs {
+                            label: label.to_string(),
+                        }));
                     }
-                    Tag::Table(_) => {
-                        return Err(FromMarkdownError::NotSupported("Table"));
+                    Tag::Table(alignments) => {
+                        let alignments = alignments.into_iter().map(alignment).collect();
+                        self.push_stack(Attrs::Table(alignments));
                     }
                     Tag::TableHead => {
-                        return Err(FromMarkdownError::NotSupported("TableHead"));
+                        self.push_stack(Attrs::TableHead);
                     }
                     Tag::TableRow => {
-                        return Err(FromMarkdownError::NotSupported("TableRow"));
+                        self.push_stack(Attrs::TableRow);
                     }
                     Tag::TableCell => {
-                        return Err(FromMarkdownError::NotSupported("TableCell"));
+                        let attrs = self.cell_attrs();
+                        self.push_stack(Attrs::TableCell(attrs));
                     }
                     Tag::Emphasis => {
                         self.mark_set.add(&MarkdownMark::Em);
@@ -146,7 +475,7 @@ impl MarkdownDeserializer {
                         self.mark_set.add(&MarkdownMark::Strong);
                     }
                     Tag::Strikethrough => {
-                        return Err(FromMarkdownError::NotSupported("Strikethrough"));
+                        self.mark_set.add(&MarkdownMark::Strikethrough);
                     }
                     Tag::Link(_, href, title) => {
                         self.mark_set.add(&MarkdownMark::Link {
@@ -159,7 +488,7 @@ impl MarkdownDeserializer {
                     Tag::Image(_, src, title) => {
                         self.push_stack(Attrs::Image(ImageAttrs {
                             src: src.to_string(),
-                            alt: title.to_string(),
+                            alt: String::new(),
                             title: title.to_string(),
                         }));
                     }
@@ -192,7 +521,7 @@ impl MarkdownDeserializer {
                         let (content, attrs) = self.pop_stack()?;
                         if let Attrs::Blockquote = attrs {
                             let b = MarkdownNode::Blockquote(Block {
-                                content: Fragment::from(content),
+                                content: fill_blocks(content, false),
                             });
                             self.add_content(b)?;
                         } else {
@@ -238,27 +567,80 @@ impl MarkdownDeserializer {
                     }
                     Tag::Item => {
                         let (content, attrs) = self.pop_stack()?;
-                        if let Attrs::ListItem = attrs {
-                            let cb = MarkdownNode::ListItem(Block {
-                                content: Fragment::from(content),
-                            });
-                            self.add_content(cb)?;
+                        match attrs {
+                            Attrs::ListItem => {
+                                let cb = MarkdownNode::ListItem(Block {
+                                    content: fill_blocks(content, true),
+                                });
+                                self.add_content(cb)?;
+                            }
+                            Attrs::TaskItem(attrs) => {
+                                let cb = MarkdownNode::TaskItem(AttrNode {
+                                    attrs,
+                                    content: fill_blocks(content, true),
+                                });
+                                self.add_content(cb)?;
+                            }
+                            _ => {
+                                return Err(FromMarkdownError::MisplacedEndTag("Item", attrs));
+                            }
                         }
                     }
                     Tag::FootnoteDefinition(_) => {
-                        return Err(FromMarkdownError::NotSupported("FootnoteDefinition"));
+                        let (content, attrs) = self.pop_stack()?;
+                        if let Attrs::FootnoteDefinition(attrs) = attrs {
+                            let f = MarkdownNode::FootnoteDefinition(AttrNode {
+                                attrs,
+                                content: fill_blocks(content, false),
+                            });
+                            self.add_content(f)?;
+                        } else {
+                            return Err(FromMarkdownError::MisplacedEndTag(
+                                "FootnoteDefinition",
+                                attrs,
+                            ));
+                        }
                     }
                     Tag::Table(_) => {
-                        return Err(FromMarkdownError::NotSupported("Table"));
-                    }
-                    Tag::TableHead => {
-                        return Err(FromMarkdownError::NotSupported("TableHead"));
+                        let (content, attrs) = self.pop_stack()?;
+                        if let Attrs::Table(_) = attrs {
+                            let t = MarkdownNode::Table(Block {
+                                content: Fragment::from(content),
+                            });
+                            self.add_content(t)?;
+                        } else {
+                            return Err(FromMarkdownError::MisplacedEndTag("Table", attrs));
+                        }
                     }
-                    Tag::TableRow => {
-                        return Err(FromMarkdownError::NotSupported("TableRow"));
+                    Tag::TableHead | Tag::TableRow => {
+                        // The header is a row of its own, so both end up as a `TableRow`
+                        let (content, attrs) = self.pop_stack()?;
+                        if let Attrs::TableHead | Attrs::TableRow = attrs {
+                            let r = MarkdownNode::TableRow(Block {
+                                content: Fragment::from(content),
+                            });
+                            self.add_content(r)?;
+                        } else {
+                            return Err(FromMarkdownError::MisplacedEndTag("TableRow", attrs));
+                        }
                     }
                     Tag::TableCell => {
-                        return Err(FromMarkdownError::NotSupported("TableCell"));
+                        let (content, attrs) = self.pop_stack()?;
+                        let is_header = matches!(self.stack.last(), Some((_, Attrs::TableHead)));
+                        if let Attrs::TableCell(attrs) = attrs {
+                            let node = AttrNode {
+                                attrs,
+                                content: Fragment::from(content),
+                            };
+                            let c = if is_header {
+                                MarkdownNode::TableHeader(node)
+                            } else {
+                                MarkdownNode::TableCell(node)
+                            };
+                            self.add_content(c)?;
+                        } else {
+                            return Err(FromMarkdownError::MisplacedEndTag("TableCell", attrs));
+                        }
                     }
                     Tag::Emphasis => {
                         self.mark_set.remove(&MarkdownMark::Em);
@@ -267,7 +649,7 @@ impl MarkdownDeserializer {
                         self.mark_set.remove(&MarkdownMark::Strong);
                     }
                     Tag::Strikethrough => {
-                        return Err(FromMarkdownError::NotSupported("Strikethrough"));
+                        self.mark_set.remove(&MarkdownMark::Strikethrough);
                     }
                     Tag::Link(_, href, title) => self.mark_set.remove(&MarkdownMark::Link {
                         attrs: LinkAttrs {
@@ -277,9 +659,12 @@ impl MarkdownDeserializer {
                     }),
                     Tag::Image(_, _, _) => {
                         let (content, attrs) = self.pop_stack()?;
-                        if let Attrs::Image(attrs) = attrs {
-                            if content.len() > 0 {
-                                return Err(FromMarkdownError::NoChildrenAllowed("Image"));
+                        if let Attrs::Image(mut attrs) = attrs {
+                            // The content of the image is its alt text
+                            for node in &content {
+                                if let MarkdownNode::Text(t) = node {
+                                    attrs.alt.push_str(t.text.as_str());
+                                }
                             }
                             let cb = MarkdownNode::Image(Leaf { attrs });
                             self.add_content(cb)?;
@@ -289,10 +674,18 @@ impl MarkdownDeserializer {
                     }
                 },
                 Event::Text(text) => {
-                    self.add_content(MarkdownNode::Text(TextNode {
-                        text: Text::from(text.to_string()),
-                        marks: self.mark_set.clone(),
-                    }))?;
+                    let (content, attrs) =
+                        self.stack.last().ok_or(FromMarkdownError::StackEmpty)?;
+                    if self.options.smart_punctuation && !matches!(attrs, Attrs::CodeBlock(_)) {
+                        let prev = match content.last() {
+                            Some(MarkdownNode::Text(t)) => t.text.as_str().chars().last(),
+                            _ => None,
+                        };
+                        let text = smart_punctuation(&text, prev);
+                        self.add_text(&text, self.mark_set.clone())?;
+                    } else {
+                        self.add_text(&text, self.mark_set.clone())?;
+                    }
                 }
                 Event::Code(text) => {
                     let mut marks = self.mark_set.clone();
@@ -302,23 +695,46 @@ impl MarkdownDeserializer {
                         marks,
                     }))?;
                 }
-                Event::Html(_) => {
-                    return Err(FromMarkdownError::NotSupported("Html"));
+                Event::Html(html) => {
+                    self.add_html(&html, range, source)?;
+                }
+                Event::FootnoteReference(label) if !self.options.footnotes => {
+                    // The reference is parsed even without the extension
+                    self.add_text(&format!("[^{}]", label), self.mark_set.clone())?;
                 }
-                Event::FootnoteReference(_) => {
-                    return Err(FromMarkdownError::NotSupported("FootnoteReference"));
+                Event::FootnoteReference(label) if !self.footnotes.contains(label.as_ref()) => {
+                    let source = format!("[^{}]", label);
+                    let err = FromMarkdownError::UndefinedFootnote(label.to_string());
+                    self.add_unsupported(err, &source)?;
                 }
-                Event::SoftBreak => {
-                    return Err(FromMarkdownError::NotSupported("SoftBreak"));
+                Event::FootnoteReference(label) => {
+                    let attrs = FootnoteAttrs {
+                        label: label.to_string(),
+                    };
+                    self.add_content(MarkdownNode::FootnoteReference(Leaf { attrs }))?;
                 }
+                Event::SoftBreak => match self.options.soft_break {
+                    SoftBreak::Space => self.add_text(" ", self.mark_set.clone())?,
+                    SoftBreak::Newline => self.add_text("\n", self.mark_set.clone())?,
+                    SoftBreak::HardBreak => self.add_content(MarkdownNode::HardBreak)?,
+                },
                 Event::HardBreak => {
                     self.add_content(MarkdownNode::HardBreak)?;
                 }
                 Event::Rule => {
                     self.add_content(MarkdownNode::HorizontalRule)?;
                 }
-                Event::TaskListMarker(_) => {
-                    return Err(FromMarkdownError::NotSupported("TaskListMarker"));
+                Event::TaskListMarker(checked) => {
+                    // The marker is the first child of the item it turns into a task
+                    match self.stack.last_mut() {
+                        Some((content, attrs @ Attrs::ListItem)) if content.is_empty() => {
+                            *attrs = Attrs::TaskItem(TaskItemAttrs { checked });
+                        }
+                        _ => {
+                            let err = FromMarkdownError::NotSupported("TaskListMarker");
+                            self.add_unsupported(err, if checked { "[x] " } else { "[ ] " })?;
+                        }
+                    }
                 }
             }
         }
@@ -335,6 +751,13 @@ impl MarkdownDeserializer {
 
 #[cfg(test)]
 mod tests {
+    use super::{
+        from_markdown, from_markdown_with, smart_punctuation, Attrs, MarkdownDeserializer,
+    };
+    use crate::markdown::{
+        helper::{code, doc, fnref, footnote, h3, li, p, pre, strike, table, task, td, th, tr, ul},
+        FromMarkdownError, MarkdownParseOptions, Unsupported,
+    };
     use pulldown_cmark::{CowStr, Event, Parser, Tag};
 
     #[test]
@@ -364,5 +787,133 @@ mod tests {
                 Event::End(Tag::Paragraph),
             ]
         );
+
+        assert_eq!(
+            from_markdown(test_string),
+            Ok(doc!(h3!("Alert Area"), p!(":::success Yes :tada: :::")).node)
+        );
+    }
+
+    #[test]
+    fn test_extensions() {
+        let text = "|a|\n|-|\n|b|\n\n~~c~~\n\n* [ ] d";
+        assert_eq!(
+            from_markdown(text),
+            Ok(doc!(
+                table!(tr!(th!("a")), tr!(td!("b"))),
+                p!(strike!("c")),
+                ul!(tight = true; task!(p!("d")))
+            )
+            .node)
+        );
+
+        let options = MarkdownParseOptions {
+            tables: false,
+            strikethrough: false,
+            tasklists: false,
+            ..MarkdownParseOptions::default()
+        };
+        assert_eq!(
+            from_markdown_with(text, &options),
+            Ok(doc!(
+                p!("|a| |-| |b|"),
+                p!("~~c~~"),
+                ul!(tight = true; li!(p!("[ ] d")))
+            )
+            .node)
+        );
+    }
+
+    #[test]
+    fn test_smart_punctuation() {
+        assert_eq!(
+            smart_punctuation("\"Don't\" -- 'no'---wait...", None),
+            "\u{201c}Don\u{2019}t\u{201d} \u{2013} \u{2018}no\u{2019}\u{2014}wait\u{2026}"
+        );
+        assert_eq!(smart_punctuation("'a", Some('b')), "\u{2019}a");
+        assert_eq!(smart_punctuation("----", None), "\u{2013}\u{2013}");
+
+        let options = MarkdownParseOptions {
+            smart_punctuation: true,
+            ..MarkdownParseOptions::default()
+        };
+        let text = "It's `'a'`...\n\n```\n\"b\"\n```";
+        assert_eq!(
+            from_markdown_with(text, &options),
+            Ok(doc!(p!("It\u{2019}s ", code!("'a'"), "\u{2026}"), pre!("\"b\"")).node)
+        );
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let text = "See[^1] and[^2].\n\n[^1]: A note";
+        let parse = |unsupported| {
+            let options = MarkdownParseOptions {
+                unsupported,
+                ..MarkdownParseOptions::default()
+            };
+            from_markdown_with(text, &options)
+        };
+        let note = || footnote!(label = "1"; p!("A note"));
+
+        assert_eq!(
+            parse(Unsupported::Error),
+            Err(FromMarkdownError::UndefinedFootnote("2".into()))
+        );
+        assert_eq!(
+            parse(Unsupported::Drop),
+            Ok(doc!(p!("See", fnref!(label = "1"), " and."), note()).node)
+        );
+        assert_eq!(
+            parse(Unsupported::PlainText),
+            Ok(doc!(p!("See", fnref!(label = "1"), " and[^2]."), note()).node)
+        );
+        assert_eq!(from_markdown(text), parse(Unsupported::PlainText));
+
+        let options = MarkdownParseOptions {
+            footnotes: false,
+            ..MarkdownParseOptions::default()
+        };
+        assert_eq!(
+            from_markdown_with("See[^2].", &options),
+            Ok(doc!(p!("See[^2].")).node)
+        );
+
+        let task = |unsupported| {
+            let events = vec![
+                Event::Start(Tag::Paragraph),
+                Event::TaskListMarker(true),
+                Event::Text(CowStr::Borrowed("a")),
+                Event::End(Tag::Paragraph),
+            ];
+            let mut d = MarkdownDeserializer {
+                options: MarkdownParseOptions {
+                    unsupported,
+                    ..MarkdownParseOptions::default()
+                },
+                ..MarkdownDeserializer::default()
+            };
+            d.deserialize("[x] a", events.into_iter().map(|event| (event, 0..0)))
+        };
+        assert_eq!(
+            task(Unsupported::Error),
+            Err(FromMarkdownError::NotSupported("TaskListMarker"))
+        );
+        assert_eq!(task(Unsupported::Drop), Ok(doc!(p!("a")).node));
+        assert_eq!(task(Unsupported::PlainText), Ok(doc!(p!("[x] a")).node));
+    }
+
+    #[test]
+    fn test_misplaced_end_tag() {
+        let events = vec![
+            Event::Start(Tag::Paragraph),
+            Event::Text(CowStr::Borrowed("a")),
+            Event::End(Tag::Item),
+        ];
+        let mut d = MarkdownDeserializer::default();
+        assert_eq!(
+            d.deserialize("a", events.into_iter().map(|event| (event, 0..0))),
+            Err(FromMarkdownError::MisplacedEndTag("Item", Attrs::Paragraph))
+        );
     }
 }
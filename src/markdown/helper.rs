@@ -2,9 +2,36 @@
 //!
 //! This module contains some functions to create nodes programmatically.
 //!
-//! See also: <https://github.com/prosemirror/prosemirror-test-builder>
-use super::{BulletListAttrs, CodeBlockAttrs, HeadingAttrs, MarkdownMark, MarkdownNode, MD};
-use crate::model::{self, AttrNode, Block, Mark, Text, TextNode};
+//! The macros are a test builder like <https://github.com/prosemirror/prosemirror-test-builder>.
+//! Text arguments may contain `<tag>`s, whose positions are recorded in the result:
+//!
+//! ```
+//! use prosemirror::markdown::helper::{doc, em, p};
+//!
+//! let doc = doc!(p!("foo<a>bar"), p!(em!("b<b>az")));
+//! assert_eq!(doc.tag("a"), 4);
+//! assert_eq!(doc.tag("b"), 10);
+//! ```
+//!
+//! Attributes are given in front of the content, e.g. `h!(level = 2; "Title")` or
+//! `a!(href = "/"; "home")`.
+//!
+//! The macros have to be imported from this module. At the crate root, where `#[macro_export]`
+//! puts them, they are hidden behind an `__md_` prefix.
+use super::{
+    Alignment, BulletListAttrs, CodeBlockAttrs, FootnoteAttrs, HeadingAttrs, HtmlAttrs, ImageAttrs,
+    LinkAttrs, MarkdownMark, MarkdownNode, MD,
+};
+use crate::model::{self, AttrNode, Block, Leaf, Mark, Tagged, TaggedNode, Text, TextNode};
+
+pub use crate::{
+    __md_a as a, __md_blockquote as blockquote, __md_br as br, __md_code as code, __md_doc as doc,
+    __md_em as em, __md_fnref as fnref, __md_footnote as footnote, __md_h as h, __md_h1 as h1,
+    __md_h2 as h2, __md_h3 as h3, __md_hr as hr, __md_html_block as html_block,
+    __md_html_inline as html_inline, __md_img as img, __md_li as li, __md_ol as ol, __md_p as p,
+    __md_pre as pre, __md_strike as strike, __md_strong as strong, __md_table as table,
+    __md_task as task, __md_td as td, __md_th as th, __md_tr as tr, __md_ul as ul,
+};
 
 type Fragment = model::Fragment<MD>;
 
@@ -104,3 +131,483 @@ pub fn blockquote<A: Into<Fragment>>(content: A) -> MarkdownNode {
 pub fn node<A: Into<MarkdownNode>>(src: A) -> MarkdownNode {
     src.into()
 }
+
+impl From<MarkdownNode> for Tagged<MD> {
+    fn from(node: MarkdownNode) -> Self {
+        let mut tagged = Tagged::default();
+        tagged.push(Tagged::node(TaggedNode::from(node)));
+        tagged
+    }
+}
+
+impl From<MarkdownNode> for TaggedNode<MD> {
+    fn from(node: MarkdownNode) -> Self {
+        Tagged::default().wrap(|_| node)
+    }
+}
+
+/// Sets an attribute from a macro argument
+#[doc(hidden)]
+pub trait AttrValue<T> {
+    fn set_to(self, target: &mut T);
+}
+
+impl AttrValue<String> for &str {
+    fn set_to(self, target: &mut String) {
+        *target = self.to_owned();
+    }
+}
+
+impl AttrValue<Option<Alignment>> for Alignment {
+    fn set_to(self, target: &mut Option<Alignment>) {
+        *target = Some(self);
+    }
+}
+
+impl<T> AttrValue<T> for T {
+    fn set_to(self, target: &mut T) {
+        *target = self;
+    }
+}
+
+#[doc(hidden)]
+pub fn build<A, F>(attrs: A, make: F, content: Vec<Tagged<MD>>) -> TaggedNode<MD>
+where
+    F: FnOnce(A, Fragment) -> MarkdownNode,
+{
+    let mut tagged = Tagged::default();
+    content.into_iter().for_each(|c| tagged.push(c));
+    tagged.wrap(|content| make(attrs, content))
+}
+
+#[doc(hidden)]
+pub fn build_marked(mark: MarkdownMark, content: Vec<Tagged<MD>>) -> Tagged<MD> {
+    let mut tagged = Tagged::default();
+    content.into_iter().for_each(|c| tagged.push(c));
+    tagged.mark(&mark)
+}
+
+#[doc(hidden)]
+pub fn block(make: fn(Block<MD>) -> MarkdownNode) -> impl FnOnce((), Fragment) -> MarkdownNode {
+    move |(), content| make(Block { content })
+}
+
+#[doc(hidden)]
+pub fn attr_node<A>(
+    make: fn(AttrNode<MD, A>) -> MarkdownNode,
+) -> impl FnOnce(A, Fragment) -> MarkdownNode {
+    move |attrs, content| make(AttrNode { attrs, content })
+}
+
+#[doc(hidden)]
+pub fn leaf(node: MarkdownNode) -> impl FnOnce((), Fragment) -> MarkdownNode {
+    move |(), _| node
+}
+
+#[doc(hidden)]
+pub fn image(attrs: ImageAttrs, _content: Fragment) -> MarkdownNode {
+    MarkdownNode::Image(Leaf { attrs })
+}
+
+#[doc(hidden)]
+pub fn footnote_reference(attrs: FootnoteAttrs, _content: Fragment) -> MarkdownNode {
+    MarkdownNode::FootnoteReference(Leaf { attrs })
+}
+
+#[doc(hidden)]
+pub fn html_block_node(attrs: HtmlAttrs, _content: Fragment) -> MarkdownNode {
+    MarkdownNode::HtmlBlock(Leaf { attrs })
+}
+
+#[doc(hidden)]
+pub fn html_inline_node(attrs: HtmlAttrs, _content: Fragment) -> MarkdownNode {
+    MarkdownNode::HtmlInline(Leaf { attrs })
+}
+
+#[doc(hidden)]
+pub fn link(attrs: LinkAttrs) -> MarkdownMark {
+    MarkdownMark::Link { attrs }
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __node {
+    ($attrs:ty, $make:expr; $($key:ident = $value:expr),+ ; $($content:expr),* $(,)?) => {{
+        let mut attrs = <$attrs as ::std::default::Default>::default();
+        $($crate::markdown::helper::AttrValue::set_to($value, &mut attrs.$key);)+
+        $crate::markdown::helper::build(attrs, $make, vec![$($crate::model::Tagged::from($content)),*])
+    }};
+    ($attrs:ty, $make:expr; $($key:ident = $value:expr),+ $(,)?) => {
+        $crate::__node!($attrs, $make; $($key = $value),+ ;)
+    };
+    ($attrs:ty, $make:expr; $($content:expr),* $(,)?) => {
+        $crate::markdown::helper::build(
+            <$attrs as ::std::default::Default>::default(),
+            $make,
+            vec![$($crate::model::Tagged::from($content)),*],
+        )
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __mark {
+    ($mark:expr; $($content:expr),* $(,)?) => {
+        $crate::markdown::helper::build_marked($mark, vec![$($crate::model::Tagged::from($content)),*])
+    };
+}
+
+/// Create a document node with tags.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_doc {
+    ($($args:tt)*) => {
+        $crate::__node!((), $crate::markdown::helper::block($crate::markdown::MarkdownNode::Doc); $($args)*)
+    };
+}
+
+/// Create a paragraph node with tags.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_p {
+    ($($args:tt)*) => {
+        $crate::__node!((), $crate::markdown::helper::block($crate::markdown::MarkdownNode::Paragraph); $($args)*)
+    };
+}
+
+/// Create a blockquote node with tags.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_blockquote {
+    ($($args:tt)*) => {
+        $crate::__node!((), $crate::markdown::helper::block($crate::markdown::MarkdownNode::Blockquote); $($args)*)
+    };
+}
+
+/// Create a list item node with tags.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_li {
+    ($($args:tt)*) => {
+        $crate::__node!((), $crate::markdown::helper::block($crate::markdown::MarkdownNode::ListItem); $($args)*)
+    };
+}
+
+/// Create a task list item with tags, e.g. `task!(checked = true; p!("Done"))`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_task {
+    ($($args:tt)*) => {
+        $crate::__node!(
+            $crate::markdown::TaskItemAttrs,
+            $crate::markdown::helper::attr_node($crate::markdown::MarkdownNode::TaskItem);
+            $($args)*
+        )
+    };
+}
+
+/// Create a heading node with tags, e.g. `h!(level = 2; "Title")`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_h {
+    ($($args:tt)*) => {
+        $crate::__node!(
+            $crate::markdown::HeadingAttrs,
+            $crate::markdown::helper::attr_node($crate::markdown::MarkdownNode::Heading);
+            $($args)*
+        )
+    };
+}
+
+/// Create a heading (level 1) node with tags.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_h1 {
+    ($($content:expr),* $(,)?) => { $crate::__md_h!(level = 1; $($content),*) };
+}
+
+/// Create a heading (level 2) node with tags.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_h2 {
+    ($($content:expr),* $(,)?) => { $crate::__md_h!(level = 2; $($content),*) };
+}
+
+/// Create a heading (level 3) node with tags.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_h3 {
+    ($($content:expr),* $(,)?) => { $crate::__md_h!(level = 3; $($content),*) };
+}
+
+/// Create a code block node with tags, e.g. `pre!(params = "rust"; "fn main() {}")`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_pre {
+    ($($args:tt)*) => {
+        $crate::__node!(
+            $crate::markdown::CodeBlockAttrs,
+            $crate::markdown::helper::attr_node($crate::markdown::MarkdownNode::CodeBlock);
+            $($args)*
+        )
+    };
+}
+
+/// Create a bullet list node with tags.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_ul {
+    ($($args:tt)*) => {
+        $crate::__node!(
+            $crate::markdown::BulletListAttrs,
+            $crate::markdown::helper::attr_node($crate::markdown::MarkdownNode::BulletList);
+            $($args)*
+        )
+    };
+}
+
+/// Create an ordered list node with tags, e.g. `ol!(order = 3; li!(p!("x")))`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_ol {
+    ($($args:tt)*) => {
+        $crate::__node!(
+            $crate::markdown::OrderedListAttrs,
+            $crate::markdown::helper::attr_node($crate::markdown::MarkdownNode::OrderedList);
+            $($args)*
+        )
+    };
+}
+
+/// Create a table node with tags.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_table {
+    ($($args:tt)*) => {
+        $crate::__node!((), $crate::markdown::helper::block($crate::markdown::MarkdownNode::Table); $($args)*)
+    };
+}
+
+/// Create a table row node with tags.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_tr {
+    ($($args:tt)*) => {
+        $crate::__node!((), $crate::markdown::helper::block($crate::markdown::MarkdownNode::TableRow); $($args)*)
+    };
+}
+
+/// Create a table header cell with tags, e.g. `th!(align = Alignment::Left; "Name")`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_th {
+    ($($args:tt)*) => {
+        $crate::__node!(
+            $crate::markdown::TableCellAttrs,
+            $crate::markdown::helper::attr_node($crate::markdown::MarkdownNode::TableHeader);
+            $($args)*
+        )
+    };
+}
+
+/// Create a table cell with tags, e.g. `td!(align = Alignment::Right; "42")`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_td {
+    ($($args:tt)*) => {
+        $crate::__node!(
+            $crate::markdown::TableCellAttrs,
+            $crate::markdown::helper::attr_node($crate::markdown::MarkdownNode::TableCell);
+            $($args)*
+        )
+    };
+}
+
+/// Create a horizontal rule.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_hr {
+    () => {
+        $crate::__node!((), $crate::markdown::helper::leaf($crate::markdown::MarkdownNode::HorizontalRule);)
+    };
+}
+
+/// Create a hard break.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_br {
+    () => {
+        $crate::__node!((), $crate::markdown::helper::leaf($crate::markdown::MarkdownNode::HardBreak);)
+    };
+}
+
+/// Create an image, e.g. `img!(src = "img.png", alt = "An image")`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_img {
+    ($($args:tt)*) => {
+        $crate::__node!($crate::markdown::ImageAttrs, $crate::markdown::helper::image; $($args)*)
+    };
+}
+
+/// Create a footnote reference, e.g. `fnref!(label = "1")`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_fnref {
+    ($($args:tt)*) => {
+        $crate::__node!($crate::markdown::FootnoteAttrs, $crate::markdown::helper::footnote_reference; $($args)*)
+    };
+}
+
+/// Create a footnote definition with tags, e.g. `footnote!(label = "1"; p!("Note"))`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_footnote {
+    ($($args:tt)*) => {
+        $crate::__node!(
+            $crate::markdown::FootnoteAttrs,
+            $crate::markdown::helper::attr_node($crate::markdown::MarkdownNode::FootnoteDefinition);
+            $($args)*
+        )
+    };
+}
+
+/// Create a block of raw HTML, e.g. `html_block!(html = "<details>")`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_html_block {
+    ($($args:tt)*) => {
+        $crate::__node!($crate::markdown::HtmlAttrs, $crate::markdown::helper::html_block_node; $($args)*)
+    };
+}
+
+/// Create inline raw HTML, e.g. `html_inline!(html = "<br>")`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_html_inline {
+    ($($args:tt)*) => {
+        $crate::__node!($crate::markdown::HtmlAttrs, $crate::markdown::helper::html_inline_node; $($args)*)
+    };
+}
+
+/// Add emphasis to the content.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_em {
+    ($($content:expr),* $(,)?) => {
+        $crate::__mark!($crate::markdown::MarkdownMark::Em; $($content),*)
+    };
+}
+
+/// Make the content strong.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_strong {
+    ($($content:expr),* $(,)?) => {
+        $crate::__mark!($crate::markdown::MarkdownMark::Strong; $($content),*)
+    };
+}
+
+/// Strike through the content.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_strike {
+    ($($content:expr),* $(,)?) => {
+        $crate::__mark!($crate::markdown::MarkdownMark::Strikethrough; $($content),*)
+    };
+}
+
+/// Mark the content as code.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_code {
+    ($($content:expr),* $(,)?) => {
+        $crate::__mark!($crate::markdown::MarkdownMark::Code; $($content),*)
+    };
+}
+
+/// Link the content, e.g. `a!(href = "/"; "home")`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __md_a {
+    ($($key:ident = $value:expr),+ ; $($content:expr),* $(,)?) => {{
+        let mut attrs = $crate::markdown::LinkAttrs::default();
+        $($crate::markdown::helper::AttrValue::set_to($value, &mut attrs.$key);)+
+        $crate::__mark!($crate::markdown::helper::link(attrs); $($content),*)
+    }};
+    ($($content:expr),* $(,)?) => {
+        $crate::__mark!($crate::markdown::helper::link(::std::default::Default::default()); $($content),*)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        a, blockquote, br, code, doc, em, h, h2, hr, img, li, node, ol, p, pre, strong, ul,
+    };
+    use crate::markdown::MarkdownNode;
+    use crate::model::Node;
+
+    #[test]
+    fn test_tags() {
+        let d = doc!(
+            p!("<a>foo<b>"),
+            blockquote!(p!("b<c>ar", em!("b<d>az"))),
+            "<e>"
+        );
+        assert_eq!(d.tag("a"), 1);
+        assert_eq!(d.tag("b"), 4);
+        assert_eq!(d.tag("c"), 8);
+        assert_eq!(d.tag("d"), 11);
+        assert_eq!(d.tag("e"), 15);
+        assert_eq!(d.tags().len(), 5);
+        assert_eq!(
+            d.to_string(),
+            r#"doc(paragraph("foo"), blockquote(paragraph("bar", em("baz"))))"#
+        );
+        assert_eq!(d.resolve(d.tag("c")).unwrap().parent_offset, 1);
+
+        // Only well-formed tags are extracted, and adjacent text is joined
+        let d = doc!(p!("a < b, <c", "<x>d"));
+        assert_eq!(d.tag("x"), 10);
+        assert_eq!(d.node, doc(p("a < b, <cd")));
+    }
+
+    #[test]
+    #[should_panic(expected = "No tag `<z>`")]
+    fn test_missing_tag() {
+        doc!(p!("foo")).tag("z");
+    }
+
+    #[test]
+    fn test_node_types() {
+        let d = doc!(
+            h!(level = 4; "a"),
+            h2!("b"),
+            pre!(params = "rust"; "fn"),
+            ol!(order = 3, tight = true; li!(p!("x"))),
+            ul!(li!(p!("y", br!(), img!(src = "i.png", alt = "i")))),
+            hr!(),
+            p!(strong!(a!(href = "/"; "l<a>ink"), code!("c"))),
+        );
+        assert_eq!(
+            d.to_string(),
+            concat!(
+                r#"doc(heading({"level": 4}, "a"), heading({"level": 2}, "b"), "#,
+                r#"code_block({"params": "rust"}, "fn"), "#,
+                r#"ordered_list({"order": 3, "tight": true}, list_item(paragraph("x"))), "#,
+                r#"bullet_list(list_item(paragraph("y", hard_break, image({"src": "i.png", "alt": "i"})))), "#,
+                r#"horizontal_rule, paragraph(strong(link({"href": "/"}, "link")), strong(code("c"))))"#,
+            )
+        );
+        assert_eq!(d.check(), Ok(()));
+        assert_eq!(d.tag("a"), 29);
+
+        // Plain nodes can be mixed in
+        let d = doc!(p("x"), p!(node("y"), MarkdownNode::HardBreak));
+        assert_eq!(
+            d.node,
+            doc((p("x"), p((node("y"), MarkdownNode::HardBreak))))
+        );
+    }
+}
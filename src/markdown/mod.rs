@@ -4,33 +4,60 @@
 //! the general JSON serialization of nodes.
 mod attrs;
 mod content;
+mod footnotes;
 pub mod helper;
+mod notation;
 mod schema;
+mod tasks;
 
 #[cfg(feature = "cmark")]
 mod from_markdown;
 #[cfg(feature = "cmark")]
 mod to_markdown;
+#[cfg(feature = "cmark")]
+mod writer;
 
+use crate::de;
 use crate::model::{
-    AttrNode, Block, Fragment, Leaf, Mark, MarkSet, MarkType, Node, Text, TextNode,
+    AttrError, AttrNode, AttrSpec, Block, Fragment, Leaf, Mark, MarkSet, MarkType, Node, Text,
+    TextNode,
 };
 pub use attrs::{
-    BulletListAttrs, CodeBlockAttrs, HeadingAttrs, ImageAttrs, LinkAttrs, OrderedListAttrs,
+    Alignment, BulletListAttrs, CodeBlockAttrs, FootnoteAttrs, HeadingAttrs, HtmlAttrs, ImageAttrs,
+    LinkAttrs, OrderedListAttrs, TableCellAttrs, TaskItemAttrs,
 };
 pub use content::MarkdownContentMatch;
+pub use footnotes::undefined_footnotes;
+pub use notation::NotationError;
 pub use schema::{MarkdownNodeType, MD};
+pub use tasks::{task_items, toggle_task};
 
 #[cfg(feature = "cmark")]
-pub use from_markdown::{from_markdown, FromMarkdownError};
+pub use from_markdown::{
+    from_markdown, from_markdown_with, FromMarkdownError, MarkdownParseOptions, SoftBreak,
+    Unsupported,
+};
 #[cfg(feature = "cmark")]
-pub use to_markdown::{to_markdown, ToMarkdownError};
+pub use to_markdown::{
+    to_markdown, to_markdown_with, Escape, HeadingStyle, MarkdownSerializerOptions, ToMarkdownError,
+};
 
 use derivative::Derivative;
 use serde::{Deserialize, Serialize};
 
+#[allow(dead_code)]
+fn assert_send_sync() {
+    use crate::util::assert_send_sync;
+    assert_send_sync::<MarkdownNode>();
+    assert_send_sync::<MarkdownMark>();
+    assert_send_sync::<crate::transform::Step<MD>>();
+}
+
 /// The node type for the markdown schema
-#[derive(Debug, Derivative, Deserialize, Serialize, PartialEq, Eq)]
+///
+/// Both `Display` and `Debug` print the notation of ProseMirror's `toString`, which can be parsed
+/// back with `FromStr`.
+#[derive(Derivative, Deserialize, Serialize, PartialEq, Eq)]
 #[derivative(Clone(bound = ""))]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum MarkdownNode {
@@ -52,12 +79,30 @@ pub enum MarkdownNode {
     OrderedList(AttrNode<MD, OrderedListAttrs>),
     /// A list item
     ListItem(Block<MD>),
+    /// A list item with a checkbox, i.e. `- [ ]`
+    TaskItem(AttrNode<MD, TaskItemAttrs>),
     /// A horizontal line `<hr>`
     HorizontalRule,
     /// A hard break `<br>`
     HardBreak,
     /// An image `<img>`
     Image(Leaf<ImageAttrs>),
+    /// A table
+    Table(Block<MD>),
+    /// A row of a table
+    TableRow(Block<MD>),
+    /// A header cell of a table
+    TableHeader(AttrNode<MD, TableCellAttrs>),
+    /// A cell of a table
+    TableCell(AttrNode<MD, TableCellAttrs>),
+    /// A reference to a footnote, i.e. `[^1]`
+    FootnoteReference(Leaf<FootnoteAttrs>),
+    /// The definition of a footnote, i.e. `[^1]: ...`
+    FootnoteDefinition(AttrNode<MD, FootnoteAttrs>),
+    /// A block of raw HTML
+    HtmlBlock(Leaf<HtmlAttrs>),
+    /// Raw HTML within text
+    HtmlInline(Leaf<HtmlAttrs>),
 }
 
 impl From<TextNode<MD>> for MarkdownNode {
@@ -90,7 +135,16 @@ impl Node<MD> for MarkdownNode {
             Self::OrderedList { .. } => true,
             Self::BulletList { .. } => true,
             Self::ListItem { .. } => true,
+            Self::TaskItem { .. } => true,
+            Self::Table { .. } => true,
+            Self::TableRow { .. } => true,
+            Self::TableHeader { .. } => true,
+            Self::TableCell { .. } => true,
+            Self::FootnoteDefinition { .. } => true,
+            Self::HtmlBlock { .. } => true,
+            Self::HtmlInline { .. } => false,
             Self::Text { .. } => false,
+            Self::FootnoteReference { .. } => false,
             Self::Image { .. } => false,
             Self::HardBreak => false,
         }
@@ -107,8 +161,17 @@ impl Node<MD> for MarkdownNode {
             Self::OrderedList { .. } => MarkdownNodeType::OrderedList,
             Self::BulletList { .. } => MarkdownNodeType::BulletList,
             Self::ListItem { .. } => MarkdownNodeType::ListItem,
+            Self::TaskItem { .. } => MarkdownNodeType::TaskItem,
             Self::Text { .. } => MarkdownNodeType::Text,
             Self::Image { .. } => MarkdownNodeType::Image,
+            Self::Table { .. } => MarkdownNodeType::Table,
+            Self::TableRow { .. } => MarkdownNodeType::TableRow,
+            Self::TableHeader { .. } => MarkdownNodeType::TableHeader,
+            Self::TableCell { .. } => MarkdownNodeType::TableCell,
+            Self::FootnoteReference { .. } => MarkdownNodeType::FootnoteReference,
+            Self::FootnoteDefinition { .. } => MarkdownNodeType::FootnoteDefinition,
+            Self::HtmlBlock { .. } => MarkdownNodeType::HtmlBlock,
+            Self::HtmlInline { .. } => MarkdownNodeType::HtmlInline,
             Self::HardBreak => MarkdownNodeType::HardBreak,
         }
     }
@@ -131,14 +194,49 @@ impl Node<MD> for MarkdownNode {
             Self::BulletList(AttrNode { content, .. }) => Some(content),
             Self::OrderedList(AttrNode { content, .. }) => Some(content),
             Self::ListItem(Block { content }) => Some(content),
+            Self::TaskItem(AttrNode { content, .. }) => Some(content),
             Self::HorizontalRule => None,
             Self::HardBreak => None,
             Self::Image { .. } => None,
+            Self::Table(Block { content }) => Some(content),
+            Self::TableRow(Block { content }) => Some(content),
+            Self::TableHeader(AttrNode { content, .. }) => Some(content),
+            Self::TableCell(AttrNode { content, .. }) => Some(content),
+            Self::FootnoteReference { .. } => None,
+            Self::HtmlBlock { .. } => None,
+            Self::HtmlInline { .. } => None,
+            Self::FootnoteDefinition(AttrNode { content, .. }) => Some(content),
         }
     }
 
     fn marks(&self) -> Option<&MarkSet<MD>> {
-        None
+        self.text_node().map(|node| &node.marks)
+    }
+
+    fn validate_attrs(&self) -> Result<(), AttrError> {
+        match self {
+            Self::Heading(AttrNode { attrs, .. }) => attrs.validate(),
+            Self::CodeBlock(AttrNode { attrs, .. }) => attrs.validate(),
+            Self::BulletList(AttrNode { attrs, .. }) => attrs.validate(),
+            Self::OrderedList(AttrNode { attrs, .. }) => attrs.validate(),
+            Self::Image(Leaf { attrs }) => attrs.validate(),
+            Self::TaskItem(AttrNode { attrs, .. }) => attrs.validate(),
+            Self::TableHeader(AttrNode { attrs, .. }) => attrs.validate(),
+            Self::TableCell(AttrNode { attrs, .. }) => attrs.validate(),
+            Self::FootnoteReference(Leaf { attrs }) => attrs.validate(),
+            Self::FootnoteDefinition(AttrNode { attrs, .. }) => attrs.validate(),
+            Self::HtmlBlock(Leaf { attrs }) => attrs.validate(),
+            Self::HtmlInline(Leaf { attrs }) => attrs.validate(),
+            Self::Doc(_)
+            | Self::Text(_)
+            | Self::Blockquote(_)
+            | Self::Paragraph(_)
+            | Self::ListItem(_)
+            | Self::Table(_)
+            | Self::TableRow(_)
+            | Self::HorizontalRule
+            | Self::HardBreak => Ok(()),
+        }
     }
 
     fn mark(&self, set: MarkSet<MD>) -> Self {
@@ -167,9 +265,18 @@ impl Node<MD> for MarkdownNode {
             Self::BulletList(node) => Self::BulletList(node.copy(map)),
             Self::OrderedList(node) => Self::OrderedList(node.copy(map)),
             Self::ListItem(block) => Self::ListItem(block.copy(map)),
+            Self::TaskItem(node) => Self::TaskItem(node.copy(map)),
             Self::HorizontalRule => Self::HorizontalRule,
             Self::HardBreak => Self::HardBreak,
             Self::Image(img) => Self::Image(img.clone()),
+            Self::Table(block) => Self::Table(block.copy(map)),
+            Self::TableRow(block) => Self::TableRow(block.copy(map)),
+            Self::TableHeader(node) => Self::TableHeader(node.copy(map)),
+            Self::TableCell(node) => Self::TableCell(node.copy(map)),
+            Self::FootnoteReference(leaf) => Self::FootnoteReference(leaf.clone()),
+            Self::FootnoteDefinition(node) => Self::FootnoteDefinition(node.copy(map)),
+            Self::HtmlBlock(leaf) => Self::HtmlBlock(leaf.clone()),
+            Self::HtmlInline(leaf) => Self::HtmlInline(leaf.clone()),
         }
     }
 }
@@ -184,17 +291,20 @@ impl From<&str> for MarkdownNode {
 #[derive(Debug, Hash, Eq, Clone, PartialEq, Deserialize, Serialize)]
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum MarkdownMark {
-    /// bold
-    Strong,
     /// italics
     Em,
-    /// monospace
-    Code,
+    /// bold
+    Strong,
+    /// struck through
+    Strikethrough,
     /// hyper-linked
     Link {
         /// The attributes
+        #[serde(deserialize_with = "de::deserialize_attrs")]
         attrs: LinkAttrs,
     },
+    /// monospace
+    Code,
 }
 
 impl Mark<MD> for MarkdownMark {
@@ -203,22 +313,49 @@ impl Mark<MD> for MarkdownMark {
             Self::Strong => MarkdownMarkType::Strong,
             Self::Em => MarkdownMarkType::Em,
             Self::Code => MarkdownMarkType::Code,
+            Self::Strikethrough => MarkdownMarkType::Strikethrough,
             Self::Link { .. } => MarkdownMarkType::Link,
         }
     }
+
+    fn validate_attrs(&self) -> Result<(), AttrError> {
+        match self {
+            Self::Link { attrs } => attrs.validate(),
+            Self::Strong | Self::Em | Self::Code | Self::Strikethrough => Ok(()),
+        }
+    }
 }
 
 /// The type of a markdown mark.
+///
+/// The order of the variants is the order of the marks in a set, as in the schema of
+/// `prosemirror-markdown`.
 #[derive(Debug, Hash, Eq, Copy, Clone, PartialEq, PartialOrd, Ord)]
 pub enum MarkdownMarkType {
-    /// bold
-    Strong,
     /// italics
     Em,
-    /// monospace
-    Code,
+    /// bold
+    Strong,
+    /// struck through
+    Strikethrough,
     /// hyper-linked
     Link,
+    /// monospace
+    Code,
 }
 
-impl MarkType for MarkdownMarkType {}
+impl MarkType for MarkdownMarkType {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Strong => "strong",
+            Self::Em => "em",
+            Self::Code => "code",
+            Self::Strikethrough => "strikethrough",
+            Self::Link => "link",
+        }
+    }
+
+    fn inclusive(self) -> bool {
+        self != Self::Link
+    }
+}
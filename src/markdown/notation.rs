@@ -0,0 +1,732 @@
+//! The compact notation of ProseMirror's `toString`, e.g.
+//! `doc(paragraph("ab"), blockquote(paragraph(em("cd"))))`.
+//!
+//! Attributes that differ from their defaults are written as an object in front of the
+//! content, e.g. `heading({"level": 2}, "Title")` or `link({"href": "/"}, "home")`. Without
+//! such attributes, the output matches the one of ProseMirror.
+use super::{
+    Alignment, BulletListAttrs, CodeBlockAttrs, FootnoteAttrs, HeadingAttrs, HtmlAttrs, ImageAttrs,
+    LinkAttrs, MarkdownMark, MarkdownNode, OrderedListAttrs, TableCellAttrs, TaskItemAttrs, MD,
+};
+use crate::model::{
+    AttrError, AttrNode, Block, Fragment, Leaf, Mark, MarkSet, MarkType, Node, NodeType, Slice,
+    TextNode,
+};
+use displaydoc::Display;
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Errors raised when parsing the notation of a node, fragment or slice
+#[derive(Debug, Clone, PartialEq, Eq, Display, Error)]
+pub enum NotationError {
+    /// Unexpected end of input
+    UnexpectedEnd,
+    /// Unexpected `{found}` at offset {pos}
+    Unexpected {
+        /// The character that was found
+        found: char,
+        /// The byte offset of the character
+        pos: usize,
+    },
+    /// Unknown node or mark type `{0}`
+    UnknownType(String),
+    /// Unknown attribute `{0}`
+    UnknownAttr(String),
+    /// Invalid value for attribute `{0}`
+    InvalidAttr(String),
+    /// Invalid attributes: {0}
+    Attrs(#[from] AttrError),
+    /// Expected a single node
+    ExpectedNode,
+}
+
+/// An attribute value
+enum Value<'a> {
+    Str(Cow<'a, str>),
+    Num(usize),
+    Bool(bool),
+    Null,
+}
+
+type Attrs<'a> = Vec<(Cow<'a, str>, Value<'a>)>;
+
+fn write_str(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+fn write_attrs(f: &mut fmt::Formatter<'_>, attrs: &[(&str, Value)]) -> fmt::Result {
+    write!(f, "{{")?;
+    for (i, (key, value)) in attrs.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write_str(f, key)?;
+        write!(f, ": ")?;
+        match value {
+            Value::Str(s) => write_str(f, s)?,
+            Value::Num(n) => write!(f, "{}", n)?,
+            Value::Bool(b) => write!(f, "{}", b)?,
+            Value::Null => write!(f, "null")?,
+        }
+    }
+    write!(f, "}}")
+}
+
+/// The attributes of a node or mark that differ from their defaults
+fn non_default<'a>(attrs: Vec<(&'a str, Value<'a>, bool)>) -> Vec<(&'a str, Value<'a>)> {
+    let changed = attrs.into_iter().filter(|(_, _, default)| !default);
+    changed.map(|(key, value, _)| (key, value)).collect()
+}
+
+fn str_attr<'a>(key: &'a str, value: &'a str) -> (&'a str, Value<'a>, bool) {
+    (key, Value::Str(Cow::Borrowed(value)), value.is_empty())
+}
+
+impl MarkdownNode {
+    fn display_attrs(&self) -> Vec<(&str, Value<'_>)> {
+        non_default(match self {
+            Self::Heading(AttrNode { attrs, .. }) => {
+                let level = attrs.level as usize;
+                vec![("level", Value::Num(level), level == 1)]
+            }
+            Self::CodeBlock(AttrNode { attrs, .. }) => vec![str_attr("params", &attrs.params)],
+            Self::BulletList(AttrNode { attrs, .. }) => {
+                vec![("tight", Value::Bool(attrs.tight), !attrs.tight)]
+            }
+            Self::OrderedList(AttrNode { attrs, .. }) => vec![
+                ("order", Value::Num(attrs.order), attrs.order == 1),
+                ("tight", Value::Bool(attrs.tight), !attrs.tight),
+            ],
+            Self::TaskItem(AttrNode { attrs, .. }) => {
+                vec![("checked", Value::Bool(attrs.checked), !attrs.checked)]
+            }
+            Self::FootnoteReference(Leaf { attrs })
+            | Self::FootnoteDefinition(AttrNode { attrs, .. }) => {
+                vec![("label", Value::Str(Cow::Borrowed(&attrs.label)), false)]
+            }
+            Self::HtmlBlock(Leaf { attrs }) | Self::HtmlInline(Leaf { attrs }) => {
+                vec![("html", Value::Str(Cow::Borrowed(&attrs.html)), false)]
+            }
+            Self::Image(Leaf { attrs }) => vec![
+                ("src", Value::Str(Cow::Borrowed(&attrs.src)), false),
+                str_attr("alt", &attrs.alt),
+                str_attr("title", &attrs.title),
+            ],
+            Self::TableHeader(AttrNode { attrs, .. }) | Self::TableCell(AttrNode { attrs, .. }) => {
+                let align = match attrs.align {
+                    Some(Alignment::Left) => Value::Str(Cow::Borrowed("left")),
+                    Some(Alignment::Center) => Value::Str(Cow::Borrowed("center")),
+                    Some(Alignment::Right) => Value::Str(Cow::Borrowed("right")),
+                    None => Value::Null,
+                };
+                vec![("align", align, attrs.align.is_none())]
+            }
+            _ => Vec::new(),
+        })
+    }
+}
+
+impl MarkdownMark {
+    fn display_attrs(&self) -> Vec<(&str, Value<'_>)> {
+        non_default(match self {
+            Self::Link { attrs } => vec![
+                ("href", Value::Str(Cow::Borrowed(&attrs.href)), false),
+                str_attr("title", &attrs.title),
+            ],
+            Self::Strong | Self::Em | Self::Code | Self::Strikethrough => Vec::new(),
+        })
+    }
+}
+
+impl fmt::Display for MarkdownNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Self::Text(TextNode { text, marks }) = self {
+            // The first mark is the outermost one
+            for mark in marks {
+                write!(f, "{}(", mark.r#type().name())?;
+                let attrs = mark.display_attrs();
+                if !attrs.is_empty() {
+                    write_attrs(f, &attrs)?;
+                    write!(f, ", ")?;
+                }
+            }
+            write_str(f, text.as_str())?;
+            return marks.into_iter().try_for_each(|_| write!(f, ")"));
+        }
+
+        write!(f, "{}", self.r#type().name())?;
+        let attrs = self.display_attrs();
+        let content = self.content().filter(|c| c.size() > 0);
+        if attrs.is_empty() && content.is_none() {
+            return Ok(());
+        }
+        write!(f, "(")?;
+        if !attrs.is_empty() {
+            write_attrs(f, &attrs)?;
+        }
+        if let Some(content) = content {
+            if !attrs.is_empty() {
+                write!(f, ", ")?;
+            }
+            content.fmt_children(f)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl fmt::Debug for MarkdownNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&mut self) -> Option<char> {
+        let rest = &self.src[self.pos..];
+        let trimmed = rest.trim_start();
+        self.pos += rest.len() - trimmed.len();
+        trimmed.chars().next()
+    }
+
+    fn unexpected(&self) -> NotationError {
+        match self.src[self.pos..].chars().next() {
+            Some(found) => NotationError::Unexpected {
+                found,
+                pos: self.pos,
+            },
+            None => NotationError::UnexpectedEnd,
+        }
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), NotationError> {
+        match self.eat(c) {
+            true => Ok(()),
+            false => Err(self.unexpected()),
+        }
+    }
+
+    fn end(&mut self) -> Result<(), NotationError> {
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => Err(self.unexpected()),
+        }
+    }
+
+    fn take_while<F: Fn(char) -> bool>(&mut self, f: F) -> &'a str {
+        self.peek();
+        let rest = &self.src[self.pos..];
+        let len = rest.find(|c| !f(c)).unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    fn ident(&mut self) -> Result<&'a str, NotationError> {
+        match self.take_while(|c| c.is_ascii_alphanumeric() || c == '_') {
+            "" => Err(self.unexpected()),
+            ident => Ok(ident),
+        }
+    }
+
+    fn string(&mut self) -> Result<Cow<'a, str>, NotationError> {
+        self.expect('"')?;
+        let start = self.pos;
+        let mut owned: Option<String> = None;
+        let mut chars = self.src[start..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos = start + i + 1;
+                    return Ok(match owned {
+                        Some(s) => Cow::Owned(s),
+                        None => Cow::Borrowed(&self.src[start..start + i]),
+                    });
+                }
+                '\\' => {
+                    let s = owned.get_or_insert_with(|| self.src[start..start + i].to_owned());
+                    let escaped = match chars.next() {
+                        Some((_, 'n')) => '\n',
+                        Some((_, 'r')) => '\r',
+                        Some((_, 't')) => '\t',
+                        Some((_, 'u')) => {
+                            let hex: String = chars.by_ref().take(4).map(|(_, c)| c).collect();
+                            let code = u32::from_str_radix(&hex, 16).ok();
+                            code.and_then(std::char::from_u32).ok_or_else(|| {
+                                self.pos = start + i;
+                                self.unexpected()
+                            })?
+                        }
+                        Some((_, c)) => c,
+                        None => break,
+                    };
+                    s.push(escaped);
+                }
+                c => {
+                    if let Some(s) = owned.as_mut() {
+                        s.push(c);
+                    }
+                }
+            }
+        }
+        self.pos = self.src.len();
+        Err(NotationError::UnexpectedEnd)
+    }
+
+    fn value(&mut self) -> Result<Value<'a>, NotationError> {
+        match self.peek() {
+            Some('"') => Ok(Value::Str(self.string()?)),
+            Some(c) if c.is_ascii_digit() => {
+                let digits = self.take_while(|c| c.is_ascii_digit());
+                digits
+                    .parse()
+                    .map(Value::Num)
+                    .map_err(|_| self.unexpected())
+            }
+            _ => match self.ident()? {
+                "true" => Ok(Value::Bool(true)),
+                "false" => Ok(Value::Bool(false)),
+                "null" => Ok(Value::Null),
+                _ => Err(self.unexpected()),
+            },
+        }
+    }
+
+    fn attrs(&mut self) -> Result<Attrs<'a>, NotationError> {
+        self.expect('{')?;
+        let mut attrs = Vec::new();
+        if self.eat('}') {
+            return Ok(attrs);
+        }
+        loop {
+            let key = self.string()?;
+            self.expect(':')?;
+            attrs.push((key, self.value()?));
+            if !self.eat(',') {
+                self.expect('}')?;
+                return Ok(attrs);
+            }
+        }
+    }
+
+    /// Parse a node, or a mark wrapping several nodes
+    fn nodes(&mut self, marks: &MarkSet<MD>) -> Result<Vec<MarkdownNode>, NotationError> {
+        if self.peek() == Some('"') {
+            let text = self.string()?;
+            let node = TextNode {
+                text: text.into_owned().into(),
+                marks: marks.clone(),
+            };
+            return Ok(vec![MarkdownNode::Text(node)]);
+        }
+
+        let name = self.ident()?;
+        let mut attrs = Attrs::new();
+        let mut content = Vec::new();
+        let mark = match mark_from_name(name) {
+            Some(make_mark) => {
+                let mut mark_attrs = Attrs::new();
+                self.expect('(')?;
+                if self.peek() == Some('{') {
+                    mark_attrs = self.attrs()?;
+                    self.eat(',');
+                }
+                let mark = make_mark(mark_attrs)?;
+                mark.validate_attrs()?;
+                Some(mark)
+            }
+            None if self.eat('(') => {
+                if self.peek() == Some('{') {
+                    attrs = self.attrs()?;
+                    self.eat(',');
+                }
+                None
+            }
+            None => return node_from_name(name, attrs, Vec::new()).map(|node| vec![node]),
+        };
+
+        let inner_marks = match &mark {
+            Some(mark) => Cow::Owned(mark.add_to_set(Cow::Borrowed(marks)).into_owned()),
+            None => Cow::Borrowed(marks),
+        };
+        if !self.eat(')') {
+            loop {
+                content.extend(self.nodes(&inner_marks)?);
+                if !self.eat(',') {
+                    self.expect(')')?;
+                    break;
+                }
+            }
+        }
+        match mark {
+            Some(_) => Ok(content),
+            None => node_from_name(name, attrs, content).map(|node| vec![node]),
+        }
+    }
+
+    fn fragment(&mut self) -> Result<Fragment<MD>, NotationError> {
+        self.expect('<')?;
+        let mut nodes = Vec::new();
+        if !self.eat('>') {
+            loop {
+                nodes.extend(self.nodes(&MarkSet::default())?);
+                if !self.eat(',') {
+                    self.expect('>')?;
+                    break;
+                }
+            }
+        }
+        Ok(Fragment::from(nodes))
+    }
+
+    fn number(&mut self) -> Result<usize, NotationError> {
+        match self.value()? {
+            Value::Num(n) => Ok(n),
+            _ => Err(self.unexpected()),
+        }
+    }
+}
+
+type MakeMark = fn(Attrs) -> Result<MarkdownMark, NotationError>;
+
+fn mark_from_name(name: &str) -> Option<MakeMark> {
+    fn no_attrs(attrs: Attrs, mark: MarkdownMark) -> Result<MarkdownMark, NotationError> {
+        match attrs.into_iter().next() {
+            Some((key, _)) => Err(NotationError::UnknownAttr(key.into_owned())),
+            None => Ok(mark),
+        }
+    }
+    Some(match name {
+        "strong" => |attrs| no_attrs(attrs, MarkdownMark::Strong),
+        "em" => |attrs| no_attrs(attrs, MarkdownMark::Em),
+        "code" => |attrs| no_attrs(attrs, MarkdownMark::Code),
+        "strikethrough" => |attrs| no_attrs(attrs, MarkdownMark::Strikethrough),
+        "link" => |attrs| {
+            let mut link = LinkAttrs {
+                href: String::new(),
+                title: String::new(),
+            };
+            for (key, value) in attrs {
+                match &*key {
+                    "href" => link.href = string_value(&key, value)?,
+                    "title" => link.title = string_value(&key, value)?,
+                    _ => return Err(NotationError::UnknownAttr(key.into_owned())),
+                }
+            }
+            Ok(MarkdownMark::Link { attrs: link })
+        },
+        _ => return None,
+    })
+}
+
+fn string_value(key: &str, value: Value) -> Result<String, NotationError> {
+    match value {
+        Value::Str(s) => Ok(s.into_owned()),
+        Value::Null => Ok(String::new()),
+        _ => Err(NotationError::InvalidAttr(key.to_owned())),
+    }
+}
+
+fn num_value(key: &str, value: Value) -> Result<usize, NotationError> {
+    match value {
+        Value::Num(n) => Ok(n),
+        _ => Err(NotationError::InvalidAttr(key.to_owned())),
+    }
+}
+
+fn bool_value(key: &str, value: Value) -> Result<bool, NotationError> {
+    match value {
+        Value::Bool(b) => Ok(b),
+        Value::Null => Ok(false),
+        _ => Err(NotationError::InvalidAttr(key.to_owned())),
+    }
+}
+
+fn align_value(key: &str, value: Value) -> Result<Option<Alignment>, NotationError> {
+    match value {
+        Value::Str(s) if s == "left" => Ok(Some(Alignment::Left)),
+        Value::Str(s) if s == "center" => Ok(Some(Alignment::Center)),
+        Value::Str(s) if s == "right" => Ok(Some(Alignment::Right)),
+        Value::Null => Ok(None),
+        _ => Err(NotationError::InvalidAttr(key.to_owned())),
+    }
+}
+
+fn node_from_name(
+    name: &str,
+    attrs: Attrs,
+    content: Vec<MarkdownNode>,
+) -> Result<MarkdownNode, NotationError> {
+    let block = || Block {
+        content: Fragment::from(content.clone()),
+    };
+    let mut node = match name {
+        "doc" => MarkdownNode::Doc(block()),
+        "paragraph" => MarkdownNode::Paragraph(block()),
+        "blockquote" => MarkdownNode::Blockquote(block()),
+        "list_item" => MarkdownNode::ListItem(block()),
+        "task_item" => MarkdownNode::TaskItem(AttrNode {
+            attrs: TaskItemAttrs::default(),
+            content: block().content,
+        }),
+        "heading" => MarkdownNode::Heading(AttrNode {
+            attrs: HeadingAttrs::default(),
+            content: block().content,
+        }),
+        "code_block" => MarkdownNode::CodeBlock(AttrNode {
+            attrs: CodeBlockAttrs::default(),
+            content: block().content,
+        }),
+        "bullet_list" => MarkdownNode::BulletList(AttrNode {
+            attrs: BulletListAttrs::default(),
+            content: block().content,
+        }),
+        "ordered_list" => MarkdownNode::OrderedList(AttrNode {
+            attrs: OrderedListAttrs::default(),
+            content: block().content,
+        }),
+        "table" => MarkdownNode::Table(block()),
+        "table_row" => MarkdownNode::TableRow(block()),
+        "table_header" => MarkdownNode::TableHeader(AttrNode {
+            attrs: TableCellAttrs::default(),
+            content: block().content,
+        }),
+        "table_cell" => MarkdownNode::TableCell(AttrNode {
+            attrs: TableCellAttrs::default(),
+            content: block().content,
+        }),
+        "footnote_reference" => MarkdownNode::FootnoteReference(Leaf {
+            attrs: FootnoteAttrs::default(),
+        }),
+        "footnote_definition" => MarkdownNode::FootnoteDefinition(AttrNode {
+            attrs: FootnoteAttrs::default(),
+            content: block().content,
+        }),
+        "html_block" => MarkdownNode::HtmlBlock(Leaf {
+            attrs: HtmlAttrs::default(),
+        }),
+        "html_inline" => MarkdownNode::HtmlInline(Leaf {
+            attrs: HtmlAttrs::default(),
+        }),
+        "horizontal_rule" => MarkdownNode::HorizontalRule,
+        "hard_break" => MarkdownNode::HardBreak,
+        "image" => MarkdownNode::Image(Leaf {
+            attrs: ImageAttrs {
+                src: String::new(),
+                alt: String::new(),
+                title: String::new(),
+            },
+        }),
+        _ => return Err(NotationError::UnknownType(name.to_owned())),
+    };
+    if node.content().is_none() && !content.is_empty() {
+        return Err(NotationError::ExpectedNode);
+    }
+
+    for (key, value) in attrs {
+        match (&mut node, &*key) {
+            (MarkdownNode::Heading(n), "level") => {
+                let level = num_value(&key, value)?;
+                n.attrs.level = level.min(u8::MAX as usize) as u8;
+            }
+            (MarkdownNode::CodeBlock(n), "params") => n.attrs.params = string_value(&key, value)?,
+            (MarkdownNode::BulletList(n), "tight") => n.attrs.tight = bool_value(&key, value)?,
+            (MarkdownNode::OrderedList(n), "order") => n.attrs.order = num_value(&key, value)?,
+            (MarkdownNode::OrderedList(n), "tight") => n.attrs.tight = bool_value(&key, value)?,
+            (MarkdownNode::TaskItem(n), "checked") => n.attrs.checked = bool_value(&key, value)?,
+            (MarkdownNode::FootnoteReference(n), "label") => {
+                n.attrs.label = string_value(&key, value)?
+            }
+            (MarkdownNode::FootnoteDefinition(n), "label") => {
+                n.attrs.label = string_value(&key, value)?
+            }
+            (MarkdownNode::HtmlBlock(n), "html") | (MarkdownNode::HtmlInline(n), "html") => {
+                n.attrs.html = string_value(&key, value)?
+            }
+            (MarkdownNode::Image(n), "src") => n.attrs.src = string_value(&key, value)?,
+            (MarkdownNode::Image(n), "alt") => n.attrs.alt = string_value(&key, value)?,
+            (MarkdownNode::Image(n), "title") => n.attrs.title = string_value(&key, value)?,
+            (MarkdownNode::TableHeader(n), "align") | (MarkdownNode::TableCell(n), "align") => {
+                n.attrs.align = align_value(&key, value)?
+            }
+            _ => return Err(NotationError::UnknownAttr(key.into_owned())),
+        }
+    }
+    node.validate_attrs()?;
+    Ok(node)
+}
+
+/// Parses the notation printed by the `Display` implementation
+impl FromStr for MarkdownNode {
+    type Err = NotationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { src: s, pos: 0 };
+        let mut nodes = parser.nodes(&MarkSet::default())?;
+        parser.end()?;
+        match (nodes.pop(), nodes.is_empty()) {
+            (Some(node), true) => Ok(node),
+            _ => Err(NotationError::ExpectedNode),
+        }
+    }
+}
+
+/// Parses a fragment, e.g. `<paragraph("a"), paragraph("b")>`
+impl FromStr for Fragment<MD> {
+    type Err = NotationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { src: s, pos: 0 };
+        let fragment = parser.fragment()?;
+        parser.end()?;
+        Ok(fragment)
+    }
+}
+
+/// Parses a slice, e.g. `<paragraph("a")>(1,1)`
+impl FromStr for Slice<MD> {
+    type Err = NotationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { src: s, pos: 0 };
+        let content = parser.fragment()?;
+        parser.expect('(')?;
+        let open_start = parser.number()?;
+        parser.expect(',')?;
+        let open_end = parser.number()?;
+        parser.expect(')')?;
+        parser.end()?;
+        Ok(Slice::new(content, open_start, open_end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NotationError;
+    use crate::markdown::{
+        helper::{blockquote, code_block, doc, em, h2, li, node, p, strong, table, td, th, tr, ul},
+        Alignment, LinkAttrs, MarkdownMark, MarkdownNode, MD,
+    };
+    use crate::model::{Fragment, Mark, MarkSet, Node, Slice};
+    use std::borrow::Cow;
+
+    /// The marks of `node` with `mark` added
+    fn with(node: &MarkdownNode, mark: MarkdownMark) -> MarkSet<MD> {
+        let marks = Cow::Borrowed(node.marks().unwrap());
+        mark.add_to_set(marks).into_owned()
+    }
+
+    fn roundtrip(node: MarkdownNode, expected: &str) {
+        assert_eq!(node.to_string(), expected);
+        assert_eq!(format!("{:?}", node), expected);
+        assert_eq!(expected.parse::<MarkdownNode>(), Ok(node));
+    }
+
+    #[test]
+    fn test_display() {
+        roundtrip(
+            doc((p("ab"), blockquote(p(em("cd"))))),
+            r#"doc(paragraph("ab"), blockquote(paragraph(em("cd"))))"#,
+        );
+        roundtrip(doc(p(Vec::new())), "doc(paragraph)");
+        roundtrip(
+            doc(p((node("a \"quoted\"\n"), strong("b")))),
+            r#"doc(paragraph("a \"quoted\"\n", strong("b")))"#,
+        );
+        roundtrip(
+            doc(vec![h2("Title"), code_block("rust", "fn"), ul(li(p("x")))]),
+            r#"doc(heading({"level": 2}, "Title"), code_block({"params": "rust"}, "fn"), bullet_list(list_item(paragraph("x"))))"#,
+        );
+
+        // Marks are nested, the first one being the outermost
+        let link = MarkdownMark::Link {
+            attrs: LinkAttrs {
+                href: "/".to_owned(),
+                title: String::new(),
+            },
+        };
+        let text = em("x").mark(with(&em("x"), link));
+        roundtrip(
+            doc(p(text)),
+            r#"doc(paragraph(em(link({"href": "/"}, "x"))))"#,
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        // Mark calls may wrap several nodes and be nested
+        let parsed: MarkdownNode = r#"doc(paragraph(em("a", strong("b")), "c"))"#.parse().unwrap();
+        let both = strong("b").mark(with(&strong("b"), MarkdownMark::Em));
+        assert_eq!(parsed, doc(p(vec![em("a"), both, node("c")])));
+
+        let fragment: Fragment<MD> = r#"<paragraph("a"), paragraph>"#.parse().unwrap();
+        assert_eq!(fragment, Fragment::from((p("a"), p(Vec::new()))));
+        assert_eq!(fragment.to_string(), r#"<paragraph("a"), paragraph>"#);
+
+        let slice: Slice<MD> = r#"<paragraph("a")>(1,1)"#.parse().unwrap();
+        assert_eq!(slice, Slice::new(Fragment::from((p("a"),)), 1, 1));
+        assert_eq!(slice.to_string(), r#"<paragraph("a")>(1,1)"#);
+
+        let err = |s: &str| s.parse::<MarkdownNode>().unwrap_err();
+        assert_eq!(err("doc(para)"), NotationError::UnknownType("para".into()));
+        assert_eq!(err("doc(paragraph"), NotationError::UnexpectedEnd);
+        assert_eq!(
+            err("doc(paragraph))"),
+            NotationError::Unexpected {
+                found: ')',
+                pos: 14
+            }
+        );
+        assert_eq!(
+            err(r#"heading({"level": 9})"#).to_string(),
+            "Invalid attributes: Attribute `level` must be between 1 and 6, got 9"
+        );
+        assert_eq!(
+            err(r#"heading({"depth": 2})"#),
+            NotationError::UnknownAttr("depth".into())
+        );
+        assert_eq!(err("horizontal_rule(\"x\")"), NotationError::ExpectedNode);
+    }
+
+    #[test]
+    fn test_table() {
+        let table = doc!(table!(
+            tr!(th!(align = Alignment::Right; "a"), th!("b")),
+            tr!(td!(align = Alignment::Right; "1"), td!()),
+        ));
+        roundtrip(
+            table.node,
+            r#"doc(table(table_row(table_header({"align": "right"}, "a"), table_header("b")), table_row(table_cell({"align": "right"}, "1"), table_cell)))"#,
+        );
+        assert_eq!(
+            r#"table_cell({"align": "top"})"#.parse::<MarkdownNode>(),
+            Err(NotationError::InvalidAttr("align".into()))
+        );
+    }
+}
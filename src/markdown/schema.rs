@@ -1,6 +1,6 @@
 use super::MarkdownMarkType;
 use crate::markdown::{MarkdownContentMatch, MarkdownMark, MarkdownNode};
-use crate::model::{ContentMatch, Fragment, MarkSet, Node, NodeType, Schema};
+use crate::model::{ContentMatch, Fragment, Mark, MarkSet, Node, NodeType, Schema};
 
 /// The markdown schema type
 pub struct MD;
@@ -34,12 +34,30 @@ pub enum MarkdownNodeType {
     OrderedList,
     /// A list item
     ListItem,
+    /// A list item with a checkbox
+    TaskItem,
     /// A horizontal line `<hr>`
     HorizontalRule,
     /// A hard break `<br>`
     HardBreak,
     /// An image `<img>`
     Image,
+    /// A table
+    Table,
+    /// A row of a table
+    TableRow,
+    /// A header cell of a table
+    TableHeader,
+    /// A cell of a table
+    TableCell,
+    /// A reference to a footnote
+    FootnoteReference,
+    /// The definition of a footnote
+    FootnoteDefinition,
+    /// A block of raw HTML
+    HtmlBlock,
+    /// Raw HTML within text
+    HtmlInline,
 }
 
 impl MarkdownNodeType {
@@ -49,18 +67,53 @@ impl MarkdownNodeType {
             | Self::Blockquote
             | Self::BulletList
             | Self::OrderedList
-            | Self::ListItem => false, // block && !textblock
-
-            Self::CodeBlock => false, // marks = ""
-
-            Self::Heading | Self::Paragraph => true, // textblock
-
-            Self::Text | Self::HorizontalRule | Self::HardBreak | Self::Image => true, // inline
+            | Self::ListItem
+            | Self::TaskItem
+            | Self::Table
+            | Self::TableRow
+            | Self::FootnoteDefinition => false, // block && !textblock
+
+            Self::CodeBlock | Self::HtmlBlock => false, // marks = ""
+
+            Self::Heading | Self::Paragraph | Self::TableHeader | Self::TableCell => true, // textblock
+
+            Self::Text
+            | Self::HorizontalRule
+            | Self::HardBreak
+            | Self::Image
+            | Self::FootnoteReference
+            | Self::HtmlInline => true, // inline
         }
     }
 }
 
 impl NodeType<MD> for MarkdownNodeType {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Doc => "doc",
+            Self::Heading => "heading",
+            Self::CodeBlock => "code_block",
+            Self::Text => "text",
+            Self::Blockquote => "blockquote",
+            Self::Paragraph => "paragraph",
+            Self::BulletList => "bullet_list",
+            Self::OrderedList => "ordered_list",
+            Self::ListItem => "list_item",
+            Self::TaskItem => "task_item",
+            Self::HorizontalRule => "horizontal_rule",
+            Self::HardBreak => "hard_break",
+            Self::Image => "image",
+            Self::Table => "table",
+            Self::TableRow => "table_row",
+            Self::TableHeader => "table_header",
+            Self::TableCell => "table_cell",
+            Self::FootnoteReference => "footnote_reference",
+            Self::FootnoteDefinition => "footnote_definition",
+            Self::HtmlBlock => "html_block",
+            Self::HtmlInline => "html_inline",
+        }
+    }
+
     fn allow_marks(self, _marks: &MarkSet<MD>) -> bool {
         self._allow_marks()
     }
@@ -70,7 +123,10 @@ impl NodeType<MD> for MarkdownNodeType {
     }
 
     fn is_inline(self) -> bool {
-        matches!(self, Self::Text | Self::Image | Self::HardBreak)
+        matches!(
+            self,
+            Self::Text | Self::Image | Self::HardBreak | Self::FootnoteReference | Self::HtmlInline
+        )
     }
 
     fn is_block(self) -> bool {
@@ -83,6 +139,9 @@ impl NodeType<MD> for MarkdownNodeType {
                 | Self::CodeBlock
                 | Self::OrderedList
                 | Self::BulletList
+                | Self::Table
+                | Self::FootnoteDefinition
+                | Self::HtmlBlock
         )
     }
 
@@ -97,9 +156,18 @@ impl NodeType<MD> for MarkdownNodeType {
             Self::BulletList => MarkdownContentMatch::ListItemPlus,
             Self::OrderedList => MarkdownContentMatch::ListItemPlus,
             Self::ListItem => MarkdownContentMatch::ParagraphBlockStar,
+            Self::TaskItem => MarkdownContentMatch::ParagraphBlockStar,
             Self::HorizontalRule => MarkdownContentMatch::Empty,
             Self::HardBreak => MarkdownContentMatch::Empty,
             Self::Image => MarkdownContentMatch::Empty,
+            Self::Table => MarkdownContentMatch::TableRowPlus,
+            Self::TableRow => MarkdownContentMatch::TableCellStar,
+            Self::TableHeader => MarkdownContentMatch::InlineStar,
+            Self::TableCell => MarkdownContentMatch::InlineStar,
+            Self::FootnoteReference => MarkdownContentMatch::Empty,
+            Self::FootnoteDefinition => MarkdownContentMatch::BlockPlus,
+            Self::HtmlBlock => MarkdownContentMatch::Empty,
+            Self::HtmlInline => MarkdownContentMatch::Empty,
         }
     }
 
@@ -115,7 +183,8 @@ impl NodeType<MD> for MarkdownNodeType {
         if let Some(m) = result {
             if m.valid_end() {
                 for child in fragment.children() {
-                    if child.marks().filter(|m| !self.allow_marks(m)).is_some() {
+                    let mut marks = child.marks().into_iter().flatten();
+                    if marks.any(|m| !self.allows_mark_type(m.r#type())) {
                         return false;
                     }
                 }
@@ -0,0 +1,85 @@
+//! Reading and toggling the items of task lists, i.e. `- [ ]` and `- [x]`.
+use super::{MarkdownNode, TaskItemAttrs, MD};
+use crate::model::{AttrNode, Fragment, Node, Slice};
+use crate::transform::{ReplaceAroundStep, Span, Step};
+
+/// The positions of all task items in the document, with their checked state
+pub fn task_items(doc: &MarkdownNode) -> Vec<(usize, bool)> {
+    doc.descendants()
+        .filter_map(|(node, pos, ..)| match node {
+            MarkdownNode::TaskItem(AttrNode { attrs, .. }) => Some((pos, attrs.checked)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Create a step that toggles the checked state of the task item directly after `pos`.
+///
+/// Like `setNodeMarkup` in ProseMirror, the step replaces the item but keeps its content.
+/// Returns `None` if there is no task item at `pos`.
+pub fn toggle_task(doc: &MarkdownNode, pos: usize) -> Option<Step<MD>> {
+    let rp = doc.resolve(pos).ok()?;
+    let node = rp.node_after()?;
+    let checked = match node.as_ref() {
+        MarkdownNode::TaskItem(AttrNode { attrs, .. }) => attrs.checked,
+        _ => return None,
+    };
+    let item = MarkdownNode::TaskItem(AttrNode {
+        attrs: TaskItemAttrs { checked: !checked },
+        content: Fragment::new(),
+    });
+    let end = pos + node.node_size();
+    Some(Step::ReplaceAround(ReplaceAroundStep {
+        span: Span { from: pos, to: end },
+        gap_from: pos + 1,
+        gap_to: end - 1,
+        slice: Slice::new(Fragment::from(vec![item]), 0, 0),
+        insert: 1,
+        structure: true,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{task_items, toggle_task};
+    use crate::markdown::helper::{doc, li, p, task, ul};
+    use crate::model::Node;
+
+    #[test]
+    fn test_toggle_task() {
+        let d = doc!(ul!(
+            "<a>",
+            task!(p!("Write")),
+            "<b>",
+            li!(p!("Note")),
+            "<c>",
+            task!(checked = true; p!("Review"))
+        ));
+        let (a, b, c) = (d.tag("a"), d.tag("b"), d.tag("c"));
+        assert_eq!(task_items(&d), vec![(a, false), (c, true)]);
+        assert_eq!(toggle_task(&d, b), None);
+        assert_eq!(toggle_task(&d, 100), None);
+
+        let step = toggle_task(&d, a).unwrap();
+        let toggled = step.apply(&d).unwrap();
+        assert_eq!(task_items(&toggled), vec![(a, true), (c, true)]);
+        assert_eq!(step.get_map().map(d.tag("c"), 1), c);
+
+        let json = serde_json::to_string(&step).unwrap();
+        assert_eq!(
+            json,
+            r#"{"stepType":"replaceAround","from":1,"to":10,"gapFrom":2,"gapTo":9,"slice":{"content":[{"type":"task_item","attrs":{"checked":true}}],"openStart":0,"openEnd":0},"insert":1,"structure":true}"#
+        );
+
+        let step = toggle_task(&toggled, c).unwrap();
+        assert_eq!(
+            step.apply(&toggled).unwrap(),
+            doc!(ul!(
+                task!(checked = true; p!("Write")),
+                li!(p!("Note")),
+                task!(p!("Review"))
+            ))
+            .node
+        );
+    }
+}
@@ -1,8 +1,10 @@
-use super::{MarkdownMark, MarkdownNode, MD};
+use super::writer::write_markdown;
+use super::{Alignment, MarkdownMark, MarkdownNode, MD};
 use crate::model::{AttrNode, Block, Fragment, Leaf, Node};
 use displaydoc::Display;
-use pulldown_cmark::{CodeBlockKind, CowStr, Event, InlineStr, LinkType, Tag};
-use pulldown_cmark_to_cmark::cmark;
+use pulldown_cmark::{
+    Alignment as CmarkAlignment, CodeBlockKind, CowStr, Event, InlineStr, LinkType, Tag,
+};
 use thiserror::Error;
 
 /// Possible error when generating markdown
@@ -18,14 +20,89 @@ impl From<std::fmt::Error> for ToMarkdownError {
     }
 }
 
+/// How headings are written
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum HeadingStyle {
+    /// `# Heading`
+    #[default]
+    Atx,
+    /// The heading underlined with `===` or `---`. Only levels 1 and 2 have this style, the other
+    /// levels are still written as ATX headings.
+    Setext,
+}
+
+/// Which characters in text are escaped with a backslash
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Escape {
+    /// Only a special character at the start of a text node, and every special character in the
+    /// alt text of images
+    #[default]
+    Leading,
+    /// Every character that could start markup, including entities and the markers of blocks
+    /// at the start of a line
+    Always,
+    /// Nothing, the text is written as is
+    Never,
+}
+
+/// Options for [`to_markdown_with`]
+///
+/// The default options write the same text as [`to_markdown`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkdownSerializerOptions {
+    /// The character of code fences, `` ` `` or `~`
+    pub fence_char: char,
+    /// The length of code fences. It grows when the code contains a run of the fence character
+    /// that is at least as long.
+    pub fence_length: usize,
+    /// The marker of bullet list items, `*`, `-` or `+`
+    pub bullet_char: char,
+    /// The delimiter of emphasis, `*` or `_`. Within a word, `*` is used either way.
+    pub emphasis_char: char,
+    /// The delimiter of strong text, `*` or `_`, which is written twice. Within a word, `*` is
+    /// used either way.
+    pub strong_char: char,
+    /// How headings are written
+    pub heading_style: HeadingStyle,
+    /// The width at which paragraphs are wrapped, if any. Lines are only broken at the spaces in
+    /// text, so they can still be longer than this.
+    pub line_width: Option<usize>,
+    /// Which characters in text are escaped
+    pub escape: Escape,
+}
+
+impl Default for MarkdownSerializerOptions {
+    fn default() -> Self {
+        Self {
+            fence_char: '`',
+            fence_length: 4,
+            bullet_char: '*',
+            emphasis_char: '*',
+            strong_char: '*',
+            heading_style: HeadingStyle::Atx,
+            line_width: None,
+            escape: Escape::Leading,
+        }
+    }
+}
+
 /// Turn a markdown document into a string
 pub fn to_markdown(doc: &MarkdownNode) -> Result<String, ToMarkdownError> {
+    to_markdown_with(doc, &MarkdownSerializerOptions::default())
+}
+
+/// Turn a markdown document into a string, with the given options
+pub fn to_markdown_with(
+    doc: &MarkdownNode,
+    options: &MarkdownSerializerOptions,
+) -> Result<String, ToMarkdownError> {
     let mut buf = String::with_capacity(doc.node_size() + 128);
     let events = MarkdownSerializer::new(doc);
-    cmark(events, &mut buf, None)?;
+    write_markdown(events, &mut buf, options)?;
     Ok(buf)
 }
 
+#[derive(Clone)]
 struct MarkdownSerializer<'a> {
     inner: Vec<(&'a MarkdownNode, usize)>,
     marks: Vec<&'a MarkdownMark>,
@@ -46,15 +123,46 @@ fn mark_tag(mark: &MarkdownMark) -> Tag {
     match mark {
         MarkdownMark::Strong => Tag::Strong,
         MarkdownMark::Em => Tag::Emphasis,
+        MarkdownMark::Strikethrough => Tag::Strikethrough,
         MarkdownMark::Code => unimplemented!("Should not be pushed on the mark stack"),
         MarkdownMark::Link { attrs } => Tag::Link(
             LinkType::Inline,
             CowStr::Borrowed(attrs.href.as_str()),
-            CowStr::Borrowed(attrs.title.as_str()),
+            link_title(&attrs.title),
         ),
     }
 }
 
+/// The title of a link or image, with its quotes escaped
+fn link_title(title: &str) -> CowStr<'_> {
+    if title.contains('"') {
+        CowStr::Boxed(title.replace('"', "\\\"").into_boxed_str())
+    } else {
+        CowStr::Borrowed(title)
+    }
+}
+
+/// The column alignments of a table, taken from its first row
+fn table_alignments(table: &Fragment<MD>) -> Vec<CmarkAlignment> {
+    let row = match table.maybe_child(0).and_then(|row| row.content()) {
+        Some(row) => row,
+        None => return vec![],
+    };
+    row.children()
+        .iter()
+        .map(|cell| match cell {
+            MarkdownNode::TableHeader(AttrNode { attrs, .. })
+            | MarkdownNode::TableCell(AttrNode { attrs, .. }) => match attrs.align {
+                None => CmarkAlignment::None,
+                Some(Alignment::Left) => CmarkAlignment::Left,
+                Some(Alignment::Center) => CmarkAlignment::Center,
+                Some(Alignment::Right) => CmarkAlignment::Right,
+            },
+            _ => CmarkAlignment::None,
+        })
+        .collect()
+}
+
 impl<'a> MarkdownSerializer<'a> {
     fn process_content(
         &mut self,
@@ -71,6 +179,41 @@ impl<'a> MarkdownSerializer<'a> {
         }
     }
 
+    /// Whether the node being processed is the child of an item in a tight list. Paragraphs can
+    /// only be kept apart by a blank line, so an item with several of them is written as loose.
+    fn in_tight_item(&self) -> bool {
+        let mut parents = self.inner.iter().rev().map(|(node, _)| *node);
+        let (item, list) = (parents.next(), parents.next());
+        let paragraphs = item.and_then(MarkdownNode::content).map_or(0, |content| {
+            content
+                .children()
+                .iter()
+                .filter(|child| matches!(child, MarkdownNode::Paragraph(_)))
+                .count()
+        });
+        match (item, list) {
+            (
+                Some(MarkdownNode::ListItem(_) | MarkdownNode::TaskItem(_)),
+                Some(MarkdownNode::BulletList(AttrNode { attrs, .. })),
+            ) => attrs.tight && paragraphs < 2,
+            (
+                Some(MarkdownNode::ListItem(_) | MarkdownNode::TaskItem(_)),
+                Some(MarkdownNode::OrderedList(AttrNode { attrs, .. })),
+            ) => attrs.tight && paragraphs < 2,
+            _ => false,
+        }
+    }
+
+    /// Whether the node being processed directly follows a block of HTML
+    fn follows_html_block(&self) -> bool {
+        let (parent, index) = match self.inner.last() {
+            Some((parent, index)) if *index >= 2 => (parent, index - 2),
+            _ => return false,
+        };
+        let prev = parent.content().and_then(|c| c.maybe_child(index));
+        matches!(prev, Some(MarkdownNode::HtmlBlock(_)))
+    }
+
     fn process_attr_node<A, F>(
         &mut self,
         index: usize,
@@ -154,15 +297,40 @@ impl<'a> Iterator for MarkdownSerializer<'a> {
                             return Some(Event::Start(mark_tag(mark)));
                         }
                     }
+                    let text = text_node.text.as_str();
+                    let in_code_block =
+                        matches!(self.inner.last(), Some((MarkdownNode::CodeBlock(_), _)));
                     if is_code {
-                        Some(Event::Code(CowStr::Borrowed(text_node.text.as_str())))
+                        Some(Event::Code(CowStr::Borrowed(text)))
+                    } else if in_code_block || !text.contains('\n') {
+                        Some(Event::Text(CowStr::Borrowed(text)))
                     } else {
-                        Some(Event::Text(CowStr::Borrowed(text_node.text.as_str())))
+                        // Newlines in the text are written as soft breaks
+                        let mut lines = text.split('\n').rev().peekable();
+                        while let Some(line) = lines.next() {
+                            if !line.is_empty() {
+                                self.stack.push(Event::Text(CowStr::Borrowed(line)));
+                            }
+                            if lines.peek().is_some() {
+                                self.stack.push(Event::SoftBreak);
+                            }
+                        }
+                        self.next()
                     }
                 }
                 MarkdownNode::Blockquote(Block { content }) => {
                     self.process_attr_node(index, content, &(), node, |()| Tag::BlockQuote)
                 }
+                MarkdownNode::Paragraph(Block { content }) if self.in_tight_item() => {
+                    // paragraphs in tight lists are written without blank lines
+                    if self.process_content(index, content, node) {
+                        if let Some(mark) = self.marks.pop() {
+                            self.inner.push((node, index));
+                            return Some(Event::End(mark_tag(mark)));
+                        }
+                    }
+                    self.next()
+                }
                 MarkdownNode::Paragraph(Block { content }) => {
                     self.process_attr_node(index, content, &(), node, |()| Tag::Paragraph)
                 }
@@ -177,19 +345,92 @@ impl<'a> Iterator for MarkdownSerializer<'a> {
                 MarkdownNode::ListItem(Block { content }) => {
                     self.process_attr_node(index, content, &(), node, |()| Tag::Item)
                 }
+                MarkdownNode::TaskItem(AttrNode { attrs, content }) => {
+                    let event = self.process_attr_node(index, content, attrs, node, |_| Tag::Item);
+                    if let Some(Event::Start(Tag::Item)) = &event {
+                        self.stack.push(Event::TaskListMarker(attrs.checked));
+                    }
+                    event
+                }
+                MarkdownNode::Table(Block { content }) => {
+                    self.process_attr_node(index, content, &(), node, |()| {
+                        Tag::Table(table_alignments(content))
+                    })
+                }
+                MarkdownNode::TableRow(Block { content }) => {
+                    // markdown tables always start with a header row
+                    let is_head = matches!(self.inner.last(), Some((_, 1)));
+                    self.process_attr_node(index, content, &(), node, |()| {
+                        if is_head {
+                            Tag::TableHead
+                        } else {
+                            Tag::TableRow
+                        }
+                    })
+                }
+                MarkdownNode::TableHeader(AttrNode { attrs, content })
+                | MarkdownNode::TableCell(AttrNode { attrs, content }) => {
+                    self.process_attr_node(index, content, attrs, node, |_| Tag::TableCell)
+                }
+                MarkdownNode::FootnoteReference(Leaf { attrs }) => Some(Event::FootnoteReference(
+                    CowStr::Borrowed(attrs.label.as_str()),
+                )),
+                MarkdownNode::FootnoteDefinition(AttrNode { attrs, content }) => self
+                    .process_attr_node(index, content, attrs, node, |attrs| {
+                        Tag::FootnoteDefinition(CowStr::Borrowed(attrs.label.as_str()))
+                    }),
+                MarkdownNode::HtmlBlock(Leaf { attrs }) => {
+                    let newline = || Event::Html(CowStr::Inlined(InlineStr::from('\n')));
+                    let html = Event::Html(CowStr::Borrowed(attrs.html.as_str()));
+                    if self.in_tight_item() {
+                        // a tight paragraph before it doesn't end its line, unless it is
+                        // empty, and the end of the item already ends the HTML
+                        let (parent, next) = *self.inner.last()?;
+                        let siblings = parent.content().map_or(0, Fragment::child_count);
+                        if next < siblings {
+                            self.stack.push(newline());
+                        }
+                        let prev = (parent.content().zip(next.checked_sub(2)))
+                            .and_then(|(content, index)| content.maybe_child(index));
+                        if matches!(prev, Some(MarkdownNode::Paragraph(Block { content })) if content.size() > 0)
+                        {
+                            self.stack.push(html);
+                            return Some(newline());
+                        }
+                        return Some(html);
+                    }
+                    self.stack.push(newline());
+                    if self.follows_html_block() {
+                        // a blank line keeps it apart from the previous block
+                        self.stack.push(html);
+                        Some(newline())
+                    } else {
+                        Some(html)
+                    }
+                }
+                MarkdownNode::HtmlInline(Leaf { attrs }) => {
+                    Some(Event::Html(CowStr::Borrowed(attrs.html.as_str())))
+                }
                 MarkdownNode::HorizontalRule => Some(Event::Rule),
                 MarkdownNode::HardBreak => {
                     // todo: inline marks
                     Some(Event::HardBreak)
                 }
                 MarkdownNode::Image(Leaf { attrs }) => {
-                    self.process_attr_node(index, Fragment::EMPTY_REF, &(), node, |()| {
-                        Tag::Image(
-                            LinkType::Inline,
-                            CowStr::Borrowed(attrs.src.as_str()),
-                            CowStr::Borrowed(attrs.src.as_str()),
-                        )
-                    })
+                    let event =
+                        self.process_attr_node(index, Fragment::EMPTY_REF, &(), node, |()| {
+                            Tag::Image(
+                                LinkType::Inline,
+                                CowStr::Borrowed(attrs.src.as_str()),
+                                link_title(&attrs.title),
+                            )
+                        });
+                    if let Some(Event::Start(Tag::Image(..))) = &event {
+                        if !attrs.alt.is_empty() {
+                            self.stack.push(Event::Text(CowStr::Borrowed(&attrs.alt)));
+                        }
+                    }
+                    event
                 }
             }
         } else {
@@ -201,11 +442,16 @@ impl<'a> Iterator for MarkdownSerializer<'a> {
 #[cfg(test)]
 mod tests {
 
-    use super::to_markdown;
+    use super::{to_markdown, to_markdown_with, Escape, HeadingStyle, MarkdownSerializerOptions};
     use crate::markdown::{
-        helper::{blockquote, code_block, doc, h1, h2, node, p, strong},
-        MarkdownNode,
+        from_markdown, from_markdown_with,
+        helper::{
+            blockquote, br, code, code_block, doc, em, fnref, footnote, h1, h2, html_block,
+            html_inline, img, li, node, ol, p, pre, strike, strong, table, task, td, th, tr, ul,
+        },
+        Alignment, FromMarkdownError, MarkdownNode, MarkdownParseOptions, SoftBreak, Unsupported,
     };
+    use crate::model::Node;
 
     fn initial_doc() -> MarkdownNode {
         doc(vec![
@@ -256,4 +502,350 @@ mod tests {
             line,
         );
     }
+
+    #[test]
+    fn test_table() {
+        let node = doc!(table!(
+            tr!(th!("Name"), th!(align = Alignment::Center; "Value")),
+            tr!(td!("a"), td!(align = Alignment::Center; strong!("1"))),
+            tr!(td!("b"), td!(align = Alignment::Center)),
+        ))
+        .node;
+        let res = "|Name|Value|\n|----|:---:|\n|a|**1**|\n|b||";
+        assert_eq!(to_markdown(&node).as_deref(), Ok(res));
+        assert_eq!(from_markdown(res), Ok(node));
+
+        // Short and non-ascii headers still get a delimiter that keeps the alignment
+        for res in ["|a|b|é|\n|--:|:-:|:--|\n|1|2|3|", "|é|\n|--:|"] {
+            let node = from_markdown(res).unwrap();
+            assert_eq!(to_markdown(&node).as_deref(), Ok(res));
+        }
+
+        let code = from_markdown("|a|\n|-|\n|`b`|").unwrap();
+        assert_eq!(code, doc!(table!(tr!(th!("a")), tr!(td!(code!("b"))))).node);
+        assert_eq!(code.check(), Ok(()));
+
+        // Pipes in cells are escaped with every option
+        let res = "|a|b|\n|-|-|\n|x \\| y|z|";
+        let node = from_markdown(res).unwrap();
+        assert_eq!(
+            node,
+            doc!(table!(tr!(th!("a"), th!("b")), tr!(td!("x | y"), td!("z")))).node
+        );
+        for escape in [Escape::Leading, Escape::Always, Escape::Never] {
+            let options = MarkdownSerializerOptions {
+                escape,
+                ..MarkdownSerializerOptions::default()
+            };
+            let out = to_markdown_with(&node, &options).unwrap();
+            assert_eq!(out, "|a|b|\n|---|---|\n|x \\| y|z|");
+            assert_eq!(from_markdown(&out).as_ref(), Ok(&node));
+        }
+    }
+
+    #[test]
+    fn test_strikethrough() {
+        let node = doc!(p!("a ", strike!("b ", strong!("c")), " d")).node;
+        let res = "a ~~b **c**~~ d";
+        assert_eq!(to_markdown(&node).as_deref(), Ok(res));
+        assert_eq!(from_markdown(res), Ok(node));
+    }
+
+    #[test]
+    fn test_task_list() {
+        let node = doc!(ul!(
+            task!(p!("a")),
+            task!(checked = true; p!("b")),
+            li!(p!("c"))
+        ))
+        .node;
+        let res = "* [ ] a\n\n* [x] b\n\n* c";
+        assert_eq!(to_markdown(&node).as_deref(), Ok(res));
+        assert_eq!(from_markdown(res), Ok(node));
+    }
+
+    #[test]
+    fn test_footnotes() {
+        let node = doc!(
+            p!("See", fnref!(label = "1"), "."),
+            footnote!(label = "1"; p!("A note"))
+        )
+        .node;
+        let res = "See[^1].\n\n[^1]: A note";
+        assert_eq!(to_markdown(&node).as_deref(), Ok(res));
+        assert_eq!(from_markdown(res), Ok(node));
+        assert_eq!(from_markdown("See[^2]."), Ok(doc!(p!("See[^2].")).node));
+        let options = MarkdownParseOptions {
+            unsupported: Unsupported::Error,
+            ..MarkdownParseOptions::default()
+        };
+        assert_eq!(
+            from_markdown_with("See[^2].", &options),
+            Err(FromMarkdownError::UndefinedFootnote("2".into()))
+        );
+    }
+
+    #[test]
+    fn test_soft_break() {
+        let text = "> a\n> b *c\n> d*";
+        let parse = |soft_break| {
+            let options = MarkdownParseOptions {
+                soft_break,
+                ..MarkdownParseOptions::default()
+            };
+            from_markdown_with(text, &options).unwrap()
+        };
+
+        let space = parse(SoftBreak::Space);
+        assert_eq!(space, doc!(blockquote!(p!("a b ", em!("c d")))).node);
+        assert_eq!(to_markdown(&space).unwrap(), "\n > \n > a b *c d*");
+
+        let newline = parse(SoftBreak::Newline);
+        assert_eq!(newline, doc!(blockquote!(p!("a\nb ", em!("c\nd")))).node);
+        assert_eq!(
+            to_markdown(&newline).unwrap(),
+            "\n > \n > a\n > b *c\n > d*"
+        );
+
+        let hard_break = parse(SoftBreak::HardBreak);
+        assert_eq!(
+            hard_break,
+            doc!(blockquote!(p!("a", br!(), "b ", em!("c", br!(), "d")))).node
+        );
+
+        let options = MarkdownParseOptions {
+            soft_break: SoftBreak::Newline,
+            ..MarkdownParseOptions::default()
+        };
+        let heading = from_markdown_with("a\nb\n===", &options).unwrap();
+        assert_eq!(heading, doc!(h1!("a\nb")).node);
+        assert_eq!(to_markdown(&heading).unwrap(), "# a b");
+        let setext = MarkdownSerializerOptions {
+            heading_style: HeadingStyle::Setext,
+            ..MarkdownSerializerOptions::default()
+        };
+        let out = to_markdown_with(&heading, &setext).unwrap();
+        assert_eq!(out, "a\nb\n=");
+        assert_eq!(from_markdown_with(&out, &options), Ok(heading));
+    }
+
+    #[test]
+    fn test_html() {
+        let res =
+            "<details>\n<summary>More</summary>\n\nHidden<br>text\n\n</details>\n\n<!-- note -->\n";
+        let node = from_markdown(res).unwrap();
+        assert_eq!(
+            node,
+            doc!(
+                html_block!(html = "<details>\n<summary>More</summary>"),
+                p!("Hidden", html_inline!(html = "<br>"), "text"),
+                html_block!(html = "</details>"),
+                html_block!(html = "<!-- note -->")
+            )
+            .node
+        );
+        assert_eq!(to_markdown(&node).as_deref(), Ok(res));
+
+        let quoted = from_markdown("> <div>\n> x\n> </div>").unwrap();
+        assert_eq!(
+            quoted,
+            doc!(blockquote!(html_block!(html = "<div>\nx\n</div>"))).node
+        );
+        let out = to_markdown(&quoted).unwrap();
+        assert_eq!(from_markdown(&out), Ok(quoted));
+
+        for inline in &["*a <b>b</b>*", "* a <b>x</b>\n* c"] {
+            let node = from_markdown(inline).unwrap();
+            assert_eq!(to_markdown(&node).as_deref(), Ok(*inline));
+        }
+    }
+
+    #[test]
+    fn test_tight_list() {
+        let res = "* a\n* *b*\n  1. c\n  1. d\n\ne";
+        let node = doc!(
+            ul!(tight = true; li!(p!("a")), li!(p!(em!("b")), ol!(tight = true; li!(p!("c")), li!(p!("d"))))),
+            p!("e")
+        )
+        .node;
+        assert_eq!(from_markdown(res), Ok(node.clone()));
+        assert_eq!(to_markdown(&node).as_deref(), Ok(res));
+
+        let loose = from_markdown("* a\n\n* b").unwrap();
+        assert_eq!(loose, doc!(ul!(li!(p!("a")), li!(p!("b")))).node);
+
+        let html = from_markdown("* <b>x</b> y\n* c").unwrap();
+        assert_eq!(
+            html,
+            doc!(ul!(tight = true;
+                li!(p!(html_inline!(html = "<b>"), "x", html_inline!(html = "</b>"), " y")),
+                li!(p!("c"))
+            ))
+            .node
+        );
+        assert_eq!(html.check(), Ok(()));
+        assert_eq!(to_markdown(&html).as_deref(), Ok("* <b>x</b> y\n* c"));
+
+        for res in &["* <div>\n* c", "* a\n  <div>\n* c"] {
+            let node = from_markdown(res).unwrap();
+            assert_eq!(node.check(), Ok(()));
+            assert_eq!(to_markdown(&node).as_deref(), Ok(*res));
+        }
+
+        // Items and quotes without content get an empty paragraph
+        let empty = from_markdown("* a\n*\n* >\n* [ ] * b").unwrap();
+        assert_eq!(
+            empty,
+            doc!(ul!(tight = true;
+                li!(p!("a")),
+                li!(p!()),
+                li!(p!(), blockquote!(p!())),
+                task!(p!(), ul!(tight = true; li!(p!("b"))))
+            ))
+            .node
+        );
+        assert_eq!(empty.check(), Ok(()));
+
+        let paragraphs = doc!(ul!(tight = true; li!(p!("a"), p!("b")))).node;
+        let out = to_markdown(&paragraphs).unwrap();
+        assert_eq!(out, "* a\n  \n  b");
+        assert_eq!(
+            from_markdown(&out),
+            Ok(doc!(ul!(li!(p!("a"), p!("b")))).node)
+        );
+    }
+
+    #[test]
+    fn test_image() {
+        let res = "![A *red* dot](dot.png \"The \\\"dot\\\"\")\n\n![](empty.png)";
+        let node = from_markdown(res).unwrap();
+        assert_eq!(
+            node,
+            doc!(
+                p!(img!(
+                    src = "dot.png",
+                    alt = "A red dot",
+                    title = "The \"dot\""
+                )),
+                p!(img!(src = "empty.png"))
+            )
+            .node
+        );
+        let out = to_markdown(&node).unwrap();
+        assert_eq!(
+            out,
+            "![A red dot](dot.png \"The \\\"dot\\\"\")\n\n![](empty.png)"
+        );
+        assert_eq!(from_markdown(&out), Ok(node));
+
+        let brackets = doc!(p!(img!(src = "x.png", alt = "a ] b [c] \\*"))).node;
+        let out = to_markdown(&brackets).unwrap();
+        assert_eq!(out, "![a \\] b \\[c\\] \\\\\\*](x.png)");
+        assert_eq!(from_markdown(&out), Ok(brackets));
+    }
+
+    #[test]
+    fn test_options() {
+        let node = doc!(
+            h1!("Title"),
+            h2!("Sub"),
+            p!("a ", em!("b"), " ", strong!("c"), " d"),
+            ul!(li!(p!("e")), li!(p!("f"))),
+            pre!(params = "md"; "```")
+        )
+        .node;
+        let options = MarkdownSerializerOptions {
+            fence_char: '~',
+            fence_length: 3,
+            bullet_char: '-',
+            emphasis_char: '_',
+            strong_char: '_',
+            heading_style: HeadingStyle::Setext,
+            ..MarkdownSerializerOptions::default()
+        };
+        let res = "Title\n=====\n\nSub\n---\n\na _b_ __c__ d\n\n- e\n\n- f\n\n~~~md\n```\n~~~";
+        assert_eq!(to_markdown_with(&node, &options).as_deref(), Ok(res));
+        assert_eq!(from_markdown(res), Ok(node.clone()));
+
+        // `_` doesn't work within words
+        let words = doc!(p!(
+            "a",
+            em!("b"),
+            "c ",
+            strong!("d"),
+            "e ",
+            em!("f"),
+            " ",
+            strong!("g")
+        ))
+        .node;
+        let res = "a*b*c **d**e _f_ __g__";
+        assert_eq!(to_markdown_with(&words, &options).as_deref(), Ok(res));
+        assert_eq!(from_markdown(res), Ok(words));
+
+        let options = MarkdownSerializerOptions {
+            fence_char: '`',
+            fence_length: 3,
+            ..MarkdownSerializerOptions::default()
+        };
+        let out = to_markdown_with(&node, &options).unwrap();
+        assert!(out.ends_with("````md\n```\n````"), "{}", out);
+    }
+
+    #[test]
+    fn test_escape() {
+        let node = doc!(p!("*a* [b] c_d")).node;
+        let escaped = |escape| {
+            let options = MarkdownSerializerOptions {
+                escape,
+                ..MarkdownSerializerOptions::default()
+            };
+            to_markdown_with(&node, &options).unwrap()
+        };
+        assert_eq!(escaped(Escape::Leading), "\\*a* [b] c_d");
+        assert_eq!(escaped(Escape::Always), "\\*a\\* \\[b\\] c\\_d");
+        assert_eq!(escaped(Escape::Never), "*a* [b] c_d");
+        assert_eq!(from_markdown(&escaped(Escape::Always)), Ok(node));
+
+        let always = MarkdownSerializerOptions {
+            escape: Escape::Always,
+            ..MarkdownSerializerOptions::default()
+        };
+        let blocks = doc!(
+            p!("- a"),
+            p!("+ b"),
+            p!("1. c"),
+            p!("2) d"),
+            p!("e\n= f"),
+            p!("&amp; g"),
+            blockquote!(p!("- h"))
+        )
+        .node;
+        let out = to_markdown_with(&blocks, &always).unwrap();
+        assert_eq!(
+            out,
+            "\\- a\n\n\\+ b\n\n1\\. c\n\n2\\) d\n\ne\n\\= f\n\n\\&amp; g\n\n > \n > \\- h"
+        );
+        let newlines = MarkdownParseOptions {
+            soft_break: SoftBreak::Newline,
+            ..MarkdownParseOptions::default()
+        };
+        assert_eq!(from_markdown_with(&out, &newlines), Ok(blocks));
+    }
+
+    #[test]
+    fn test_line_width() {
+        let node = doc!(
+            blockquote!(p!("one two three ", strong!("four"), " five - six")),
+            h1!("a long heading that stays on one line")
+        )
+        .node;
+        let options = MarkdownSerializerOptions {
+            line_width: Some(12),
+            ..MarkdownSerializerOptions::default()
+        };
+        let res = "\n > \n > one two\n > three **four**\n > five -\n > six\n\n# a long heading that stays on one line";
+        assert_eq!(to_markdown_with(&node, &options).as_deref(), Ok(res));
+        assert_eq!(from_markdown(res), Ok(node));
+    }
 }
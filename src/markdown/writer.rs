@@ -0,0 +1,529 @@
+// Derived from the `cmark` function of pulldown-cmark-to-cmark 4.0.2, copyright Sebastian Thiel
+// and Dylan Owen, licensed under the Apache License, Version 2.0 (see LICENSE-APACHE and NOTICE).
+// Modified to support the options of `MarkdownSerializerOptions`.
+
+//! Writing the events of the [`MarkdownSerializer`](super::to_markdown) as text.
+//!
+//! This started out as the `cmark` function of `pulldown-cmark-to-cmark`, and still writes the
+//! same text for the default options.
+use super::{Escape, HeadingStyle, MarkdownSerializerOptions};
+use pulldown_cmark::{Alignment, CodeBlockKind, Event, Tag};
+use std::borrow::Cow;
+use std::fmt::{self, Write};
+use std::iter::Peekable;
+
+/// The characters that are escaped at the start of a text
+const SPECIAL_CHARACTERS: &str = "#\\_*<>`|[";
+/// The characters that are escaped anywhere in a text with [`Escape::Always`] and in alt text
+const ALWAYS_ESCAPED: &str = "#\\_*<>`|[]~&";
+
+const NEWLINES_AFTER_BLOCK: usize = 2;
+const NEWLINES_AFTER_REST: usize = 1;
+
+/// Write the events to `out`, in the style of the options
+pub(crate) fn write_markdown<'a, I, W>(
+    events: I,
+    out: W,
+    options: &MarkdownSerializerOptions,
+) -> fmt::Result
+where
+    I: Iterator<Item = Event<'a>> + Clone,
+    W: Write,
+{
+    let mut writer = MarkdownWriter {
+        out,
+        options,
+        column: 0,
+        last_char: None,
+        newlines_before_start: 0,
+        lists: vec![],
+        padding: vec![],
+        table_alignments: vec![],
+        table_headers: vec![],
+        store_next_text: false,
+        text_for_header: None,
+        code_fence: None,
+        last_was_html: false,
+        in_heading: false,
+        setext: None,
+        in_table: false,
+        in_image: false,
+        delimiters: vec![],
+    };
+    let mut events = events.peekable();
+    while let Some(event) = events.next() {
+        writer.event(event, &mut events)?;
+    }
+    Ok(())
+}
+
+struct MarkdownWriter<'o, W> {
+    out: W,
+    options: &'o MarkdownSerializerOptions,
+    /// The number of characters since the last newline
+    column: usize,
+    /// The amount of newlines to insert before the next block
+    newlines_before_start: usize,
+    /// The start of the ordered lists we are in, `None` for bullet lists
+    lists: Vec<Option<u64>>,
+    /// The prefix to print after each newline
+    padding: Vec<String>,
+    table_alignments: Vec<Alignment>,
+    table_headers: Vec<String>,
+    /// Whether the next text is stored as the header of the table column
+    store_next_text: bool,
+    text_for_header: Option<String>,
+    /// The fence of the code block we are in
+    code_fence: Option<String>,
+    /// Whether the last event ended a block of HTML, after which blocks need an extra newline
+    last_was_html: bool,
+    in_heading: bool,
+    /// The column at which the text of a setext heading starts
+    setext: Option<usize>,
+    in_table: bool,
+    /// Alt text is written as plain text, so markup in it is always escaped
+    in_image: bool,
+    /// The last character written
+    last_char: Option<char>,
+    /// The characters of the open emphasis and strong delimiters
+    delimiters: Vec<char>,
+}
+
+impl<'o, W: Write> MarkdownWriter<'o, W> {
+    fn write(&mut self, text: &str) -> fmt::Result {
+        match text.rfind('\n') {
+            Some(pos) => self.column = text[pos + 1..].chars().count(),
+            None => self.column += text.chars().count(),
+        }
+        if let Some(c) = text.chars().last() {
+            self.last_char = Some(c);
+        }
+        self.out.write_str(text)
+    }
+
+    fn newline(&mut self) -> fmt::Result {
+        let padding = self.padding.concat();
+        self.write("\n")?;
+        self.write(&padding)
+    }
+
+    fn consume_newlines(&mut self) -> fmt::Result {
+        while self.newlines_before_start != 0 {
+            self.newlines_before_start -= 1;
+            self.newline()?;
+        }
+        Ok(())
+    }
+
+    fn newlines_after(&mut self, count: usize) {
+        self.newlines_before_start = self.newlines_before_start.max(count);
+    }
+
+    fn padding_width(&self) -> usize {
+        self.padding.iter().map(|p| p.chars().count()).sum()
+    }
+
+    /// Write the text, with the newlines followed by the padding
+    fn write_lines(&mut self, text: &str, wrap: bool) -> fmt::Result {
+        for (i, line) in text.split('\n').enumerate() {
+            if i > 0 {
+                self.newline()?;
+            }
+            if wrap {
+                self.write_line(line)?;
+            } else {
+                self.write(line)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_line(&mut self, line: &str) -> fmt::Result {
+        let width = match self.options.line_width {
+            Some(width) if self.code_fence.is_none() && !self.in_heading && !self.in_table => width,
+            _ => return self.write(line),
+        };
+        for (i, word) in line.split(' ').enumerate() {
+            if i > 0 {
+                let fits = self.column + 1 + word.chars().count() <= width;
+                if fits
+                    || word.is_empty()
+                    || self.column <= self.padding_width()
+                    || starts_block(word)
+                {
+                    self.write(" ")?;
+                } else {
+                    self.newline()?;
+                }
+            }
+            self.write(word)?;
+        }
+        Ok(())
+    }
+
+    fn escape<'t>(&self, text: &'t str) -> Cow<'t, str> {
+        if self.code_fence.is_some() {
+            return text.into();
+        }
+        match self.options.escape {
+            Escape::Leading if self.in_image => escape_all(text),
+            // A pipe ends the cell, whatever the options
+            Escape::Leading | Escape::Never if self.in_table && text.contains('|') => {
+                let pipes = text.replace('|', "\\|");
+                match text.chars().next() {
+                    Some(c)
+                        if self.options.escape == Escape::Leading
+                            && c != '|'
+                            && SPECIAL_CHARACTERS.contains(c) =>
+                    {
+                        format!("\\{}", pipes).into()
+                    }
+                    _ => pipes.into(),
+                }
+            }
+            Escape::Leading => match text.chars().next() {
+                Some(c) if SPECIAL_CHARACTERS.contains(c) => format!("\\{}", text).into(),
+                _ => text.into(),
+            },
+            Escape::Always => {
+                let escaped = escape_all(text);
+                if self.column > self.padding_width() || !starts_block(&escaped) {
+                    return escaped;
+                }
+                // The markers that `escape_all` leaves alone, like `-` or `1.`
+                let digits = escaped.len()
+                    - escaped
+                        .trim_start_matches(|c: char| c.is_ascii_digit())
+                        .len();
+                format!("{}\\{}", &escaped[..digits], &escaped[digits..]).into()
+            }
+            Escape::Never => text.into(),
+        }
+    }
+
+    /// The delimiter character for emphasis that starts here. `_` can't open or close emphasis
+    /// within a word, so `*` is used there instead.
+    fn open_delimiter<'a, I>(&mut self, c: char, rest: &Peekable<I>) -> char
+    where
+        I: Iterator<Item = Event<'a>> + Clone,
+    {
+        let in_word = |c: Option<char>| matches!(c, Some(c) if c.is_alphanumeric());
+        let c = if c == '_' && (in_word(self.last_char) || in_word(char_after_end(rest))) {
+            '*'
+        } else {
+            c
+        };
+        self.delimiters.push(c);
+        c
+    }
+
+    fn close_delimiter(&mut self) -> char {
+        self.delimiters.pop().unwrap_or('*')
+    }
+
+    fn event<'a, I>(&mut self, event: Event<'a>, rest: &mut Peekable<I>) -> fmt::Result
+    where
+        I: Iterator<Item = Event<'a>> + Clone,
+    {
+        // Markdown inside of HTML is only parsed after a blank line
+        if self.last_was_html && !matches!(event, Event::Html(_) | Event::Text(_)) {
+            self.write("\n")?;
+        }
+        self.last_was_html = false;
+
+        match event {
+            Event::Rule => {
+                self.consume_newlines()?;
+                self.newlines_after(NEWLINES_AFTER_BLOCK);
+                self.write("---")
+            }
+            Event::Code(text) => {
+                if self.store_next_text {
+                    self.store_next_text = false;
+                    self.text_for_header = Some(format!("`{}`", text));
+                }
+                self.write("`")?;
+                self.write(&text)?;
+                self.write("`")
+            }
+            Event::Start(tag) => self.start(tag, rest),
+            Event::End(tag) => self.end(tag),
+            Event::HardBreak => {
+                self.write("  ")?;
+                self.newline()
+            }
+            // An ATX heading ends at the end of its line
+            Event::SoftBreak if self.in_heading && self.setext.is_none() => self.write(" "),
+            Event::SoftBreak => self.newline(),
+            Event::Text(text) => {
+                if self.store_next_text {
+                    self.store_next_text = false;
+                    self.text_for_header = Some(text.to_string());
+                }
+                self.consume_newlines()?;
+                let text = self.escape(&text);
+                self.write_lines(&text, true)
+            }
+            Event::Html(html) => {
+                // Block HTML ends with a line break, inline HTML never does
+                self.last_was_html = html.ends_with('\n');
+                self.consume_newlines()?;
+                self.write_lines(&html, false)
+            }
+            Event::FootnoteReference(label) => self.write(&format!("[^{}]", label)),
+            Event::TaskListMarker(checked) => self.write(if checked { "[x] " } else { "[ ] " }),
+        }
+    }
+
+    fn start<'a, I>(&mut self, tag: Tag<'a>, rest: &mut Peekable<I>) -> fmt::Result
+    where
+        I: Iterator<Item = Event<'a>> + Clone,
+    {
+        if let Tag::List(start) = tag {
+            self.lists.push(start);
+            if self.lists.len() > 1 {
+                self.newlines_after(NEWLINES_AFTER_REST);
+            }
+        }
+        let consumed_newlines = self.newlines_before_start != 0;
+        self.consume_newlines()?;
+        match tag {
+            Tag::Item => match self.lists.last().copied() {
+                Some(Some(n)) => {
+                    let marker = format!("{}. ", n);
+                    self.padding.push(" ".repeat(marker.len()));
+                    self.write(&marker)
+                }
+                Some(None) => {
+                    self.padding.push("  ".into());
+                    let marker = format!("{} ", self.options.bullet_char);
+                    self.write(&marker)
+                }
+                None => Ok(()),
+            },
+            Tag::Table(alignments) => {
+                self.table_alignments = alignments;
+                self.in_table = true;
+                Ok(())
+            }
+            Tag::TableHead | Tag::TableRow => Ok(()),
+            Tag::TableCell => {
+                self.store_next_text = true;
+                self.write("|")
+            }
+            Tag::Link(..) => self.write("["),
+            Tag::Image(..) => {
+                self.in_image = true;
+                self.write("![")
+            }
+            Tag::Emphasis => {
+                let c = self.open_delimiter(self.options.emphasis_char, rest);
+                self.write(c.encode_utf8(&mut [0; 4]))
+            }
+            Tag::Strong => {
+                let c = self.open_delimiter(self.options.strong_char, rest);
+                self.write(&format!("{}{}", c, c))
+            }
+            Tag::Strikethrough => self.write("~~"),
+            Tag::FootnoteDefinition(label) => self.write(&format!("[^{}]: ", label)),
+            Tag::Paragraph | Tag::List(_) => Ok(()),
+            Tag::Heading(level) => {
+                let setext = self.options.heading_style == HeadingStyle::Setext
+                    && level <= 2
+                    && !matches!(rest.peek(), Some(Event::End(_)));
+                self.in_heading = true;
+                if setext {
+                    self.setext = Some(self.column);
+                    Ok(())
+                } else {
+                    self.write(&format!("{} ", "#".repeat(level as usize)))
+                }
+            }
+            Tag::BlockQuote => {
+                self.padding.push(" > ".into());
+                self.newlines_before_start = 1;
+                // after some newlines, the padding of the new level is still missing
+                if consumed_newlines {
+                    self.write(" > ")
+                } else {
+                    self.newline()
+                }
+            }
+            Tag::CodeBlock(kind) => {
+                let fence = self.fence(rest.peek());
+                let info = match kind {
+                    CodeBlockKind::Indented => "".into(),
+                    CodeBlockKind::Fenced(info) => info,
+                };
+                if !consumed_newlines {
+                    self.newline()?;
+                }
+                self.write(&fence)?;
+                self.write(&info)?;
+                self.code_fence = Some(fence);
+                self.newline()
+            }
+        }
+    }
+
+    /// The fence of a code block, which is longer than any run of the fence character in the code
+    fn fence(&self, code: Option<&Event>) -> String {
+        let c = self.options.fence_char;
+        let mut length = self.options.fence_length.max(3);
+        if let Some(Event::Text(code)) = code {
+            let mut run = 0;
+            for next in code.chars() {
+                run = if next == c { run + 1 } else { 0 };
+                length = length.max(run + 1);
+            }
+        }
+        c.to_string().repeat(length)
+    }
+
+    fn end(&mut self, tag: Tag) -> fmt::Result {
+        match tag {
+            Tag::Image(_, uri, title) | Tag::Link(_, uri, title) => {
+                self.in_image = false;
+                if title.is_empty() {
+                    self.write(&format!("]({})", uri))
+                } else {
+                    self.write(&format!("]({} \"{}\")", uri, title))
+                }
+            }
+            Tag::Emphasis => {
+                let c = self.close_delimiter();
+                self.write(c.encode_utf8(&mut [0; 4]))
+            }
+            Tag::Strong => {
+                let c = self.close_delimiter();
+                self.write(&format!("{}{}", c, c))
+            }
+            Tag::Strikethrough => self.write("~~"),
+            Tag::Heading(level) => {
+                self.newlines_after(NEWLINES_AFTER_BLOCK);
+                self.in_heading = false;
+                match self.setext.take() {
+                    Some(start) => {
+                        let width = self.column.saturating_sub(start).max(1);
+                        let underline = if level == 1 { "=" } else { "-" };
+                        self.newline()?;
+                        self.write(&underline.repeat(width))
+                    }
+                    None => Ok(()),
+                }
+            }
+            Tag::Paragraph => {
+                self.newlines_after(NEWLINES_AFTER_BLOCK);
+                Ok(())
+            }
+            Tag::CodeBlock(_) => {
+                self.newlines_after(NEWLINES_AFTER_BLOCK);
+                match self.code_fence.take() {
+                    Some(fence) => self.write(&fence),
+                    None => Ok(()),
+                }
+            }
+            Tag::Table(_) => {
+                self.newlines_after(NEWLINES_AFTER_BLOCK);
+                self.table_alignments.clear();
+                self.table_headers.clear();
+                self.in_table = false;
+                Ok(())
+            }
+            Tag::TableCell => {
+                let header = self.text_for_header.take();
+                self.table_headers
+                    .push(header.unwrap_or_else(|| "  ".into()));
+                Ok(())
+            }
+            Tag::TableRow => {
+                self.newlines_after(NEWLINES_AFTER_REST);
+                self.write("|")
+            }
+            Tag::TableHead => {
+                self.newlines_after(NEWLINES_AFTER_REST);
+                self.write("|")?;
+                self.newline()?;
+                let mut delimiter = String::new();
+                for (alignment, name) in self.table_alignments.iter().zip(&self.table_headers) {
+                    delimiter.push('|');
+                    // As wide as the header, but with at least three characters
+                    let width = name.chars().count().max(3);
+                    let last = width - 1;
+                    for c in 0..width {
+                        let colon = match alignment {
+                            Alignment::Left => c == 0,
+                            Alignment::Center => c == 0 || c == last,
+                            Alignment::Right => c == last,
+                            Alignment::None => false,
+                        };
+                        delimiter.push(if colon { ':' } else { '-' });
+                    }
+                }
+                delimiter.push('|');
+                self.write(&delimiter)
+            }
+            Tag::Item => {
+                self.padding.pop();
+                self.newlines_after(NEWLINES_AFTER_REST);
+                Ok(())
+            }
+            Tag::List(_) => {
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.newlines_after(NEWLINES_AFTER_BLOCK);
+                }
+                Ok(())
+            }
+            Tag::BlockQuote => {
+                self.padding.pop();
+                self.newlines_after(NEWLINES_AFTER_BLOCK);
+                Ok(())
+            }
+            Tag::FootnoteDefinition(_) => Ok(()),
+        }
+    }
+}
+
+/// Escape every character that could be read as markup
+fn escape_all(text: &str) -> Cow<'_, str> {
+    if !text.contains(|c| ALWAYS_ESCAPED.contains(c)) {
+        return text.into();
+    }
+    let mut escaped = String::with_capacity(text.len() + 8);
+    for c in text.chars() {
+        if ALWAYS_ESCAPED.contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped.into()
+}
+
+/// The first character of the text after the end of the tag that was just started
+fn char_after_end<'a, I>(rest: &Peekable<I>) -> Option<char>
+where
+    I: Iterator<Item = Event<'a>> + Clone,
+{
+    let mut events = rest.clone();
+    let mut depth = 0;
+    loop {
+        match events.next()? {
+            Event::Start(_) => depth += 1,
+            Event::End(_) if depth == 0 => break,
+            Event::End(_) => depth -= 1,
+            _ => {}
+        }
+    }
+    match events.next()? {
+        Event::Text(text) => text.chars().next(),
+        _ => None,
+    }
+}
+
+/// Whether a word at the start of a line would be read as the start of a block
+fn starts_block(word: &str) -> bool {
+    let digits = word.trim_start_matches(|c: char| c.is_ascii_digit());
+    word.starts_with(|c| "#>-+*=|`~".contains(c))
+        || (digits.len() < word.len() && digits.starts_with(['.', ')']))
+}
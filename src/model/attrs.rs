@@ -0,0 +1,78 @@
+use displaydoc::Display;
+use thiserror::Error;
+
+/// Errors raised when an attribute value does not conform to its spec
+#[derive(Debug, Clone, PartialEq, Eq, Display, Error)]
+pub enum AttrError {
+    /// Attribute `{name}` must be between {min} and {max}, got {value}
+    OutOfRange {
+        /// The name of the attribute
+        name: &'static str,
+        /// The value that was found
+        value: i64,
+        /// The smallest allowed value
+        min: i64,
+        /// The largest allowed value
+        max: i64,
+    },
+    /// Attribute `{name}` may not be `{value}`
+    NotAllowed {
+        /// The name of the attribute
+        name: &'static str,
+        /// The value that was found
+        value: String,
+    },
+}
+
+impl AttrError {
+    /// Check that `value` lies within `min..=max`
+    pub fn check_range<T: Into<i64>>(
+        name: &'static str,
+        value: T,
+        min: i64,
+        max: i64,
+    ) -> Result<(), AttrError> {
+        let value = value.into();
+        if value < min || value > max {
+            Err(AttrError::OutOfRange {
+                name,
+                value,
+                min,
+                max,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Check that `value` is one of the allowed values, as decided by `allowed`
+    pub fn check_allowed(
+        name: &'static str,
+        value: &str,
+        allowed: impl FnOnce(&str) -> bool,
+    ) -> Result<(), AttrError> {
+        if allowed(value) {
+            Ok(())
+        } else {
+            Err(AttrError::NotAllowed {
+                name,
+                value: value.to_owned(),
+            })
+        }
+    }
+}
+
+/// The spec for the attributes of a node or mark.
+///
+/// Defaults for missing attributes are provided through `serde`, i.e. the `Default`
+/// implementation and `#[serde(default)]` fields of the implementing type. This trait adds
+/// the constraints that can't be expressed in the type itself, like allowed values and ranges.
+/// It is checked whenever attributes are deserialized as part of a node, mark or step.
+pub trait AttrSpec {
+    /// Check whether the attribute values are allowed by this spec.
+    fn validate(&self) -> Result<(), AttrError> {
+        Ok(())
+    }
+}
+
+impl AttrSpec for () {}
@@ -0,0 +1,176 @@
+use super::{Fragment, Mark, MarkSet, Node, Schema, Text, TextNode};
+use derivative::Derivative;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::Deref;
+
+/// A sequence of nodes with named positions, as created by a test builder.
+///
+/// Tags are written as `<name>` inside of text, e.g. `"foo<a>bar"`, and their positions are
+/// relative to the start of the sequence.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct Tagged<S: Schema> {
+    nodes: Vec<S::Node>,
+    tags: HashMap<String, usize>,
+    size: usize,
+}
+
+impl<S: Schema> Default for Tagged<S> {
+    fn default() -> Self {
+        Tagged {
+            nodes: Vec::new(),
+            tags: HashMap::new(),
+            size: 0,
+        }
+    }
+}
+
+impl<S: Schema> Tagged<S> {
+    /// Create text nodes with the given marks, extracting the `<tag>`s from the text.
+    pub fn text(src: &str, marks: &MarkSet<S>) -> Self {
+        let mut tagged = Tagged::default();
+        let mut text = String::new();
+        let mut rest = src;
+        while let Some(start) = rest.find('<') {
+            let after = &rest[start + 1..];
+            let len = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .filter(|&len| len > 0 && after[len..].starts_with('>'));
+            text.push_str(&rest[..start]);
+            match len {
+                Some(len) => {
+                    let pos = text.encode_utf16().count();
+                    tagged.tags.insert(after[..len].to_owned(), pos);
+                    rest = &after[len + 1..];
+                }
+                None => {
+                    text.push('<');
+                    rest = after;
+                }
+            }
+        }
+        text.push_str(rest);
+        if !text.is_empty() {
+            tagged.push_node(S::Node::new_text_node(TextNode {
+                text: Text::from(text),
+                marks: marks.clone(),
+            }));
+        }
+        tagged
+    }
+
+    /// Wrap a single node, whose tags are relative to the start of its content.
+    pub fn node(node: TaggedNode<S>) -> Self {
+        let mut tagged = Tagged::default();
+        tagged.push_node(node.node);
+        tagged.tags = node.tags.into_iter().map(|(k, v)| (k, v + 1)).collect();
+        tagged
+    }
+
+    fn push_node(&mut self, node: S::Node) {
+        self.size += node.node_size();
+        if let (Some(last), Some(text)) = (self.nodes.last_mut(), node.text_node()) {
+            if let Some(prev) = text.same_markup(last) {
+                *last = S::Node::new_text_node(prev.with_text(prev.text.join(&text.text)));
+                return;
+            }
+        }
+        self.nodes.push(node);
+    }
+
+    /// Append other tagged content, shifting its tags
+    pub fn push(&mut self, other: Tagged<S>) {
+        let offset = self.size;
+        let tags = other.tags.into_iter().map(|(k, v)| (k, v + offset));
+        self.tags.extend(tags);
+        for node in other.nodes {
+            self.push_node(node);
+        }
+    }
+
+    /// Add the mark to all inline nodes
+    pub fn mark(self, mark: &S::Mark) -> Self {
+        let nodes = self.nodes.into_iter().map(|node| match node.marks() {
+            Some(marks) if node.is_inline() => {
+                node.mark(mark.add_to_set(Cow::Borrowed(marks)).into_owned())
+            }
+            _ => node,
+        });
+        Tagged {
+            nodes: nodes.collect(),
+            ..self
+        }
+    }
+
+    /// Create a node from this content. The tags of the result are relative to its content.
+    pub fn wrap<F>(self, f: F) -> TaggedNode<S>
+    where
+        F: FnOnce(Fragment<S>) -> S::Node,
+    {
+        TaggedNode {
+            node: f(Fragment::from(self.nodes)),
+            tags: self.tags,
+        }
+    }
+
+    /// The position of the given tag, if present
+    pub fn tag(&self, name: &str) -> Option<usize> {
+        self.tags.get(name).copied()
+    }
+
+    /// The nodes as a fragment
+    pub fn into_fragment(self) -> Fragment<S> {
+        Fragment::from(self.nodes)
+    }
+}
+
+impl<S: Schema> From<&str> for Tagged<S> {
+    fn from(text: &str) -> Self {
+        Tagged::text(text, &MarkSet::default())
+    }
+}
+
+impl<S: Schema> From<TaggedNode<S>> for Tagged<S> {
+    fn from(node: TaggedNode<S>) -> Self {
+        Tagged::node(node)
+    }
+}
+
+/// A node created by a test builder, with the positions of the `<tag>`s in its content.
+///
+/// It dereferences to the node, so that e.g. `doc.resolve(doc.tag("a"))` works.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct TaggedNode<S: Schema> {
+    /// The node
+    pub node: S::Node,
+    tags: HashMap<String, usize>,
+}
+
+impl<S: Schema> TaggedNode<S> {
+    /// The position of the given tag, relative to the start of the content of the node.
+    ///
+    /// # Panics
+    ///
+    /// If there is no such tag.
+    pub fn tag(&self, name: &str) -> usize {
+        match self.tags.get(name) {
+            Some(&pos) => pos,
+            None => panic!("No tag `<{}>` in {}", name, self.node),
+        }
+    }
+
+    /// All the tags of this node
+    pub fn tags(&self) -> &HashMap<String, usize> {
+        &self.tags
+    }
+}
+
+impl<S: Schema> Deref for TaggedNode<S> {
+    type Target = S::Node;
+
+    fn deref(&self) -> &S::Node {
+        &self.node
+    }
+}
@@ -0,0 +1,92 @@
+use super::{Fragment, Node, Schema};
+
+/// An item yielded by [`Descendants`]: the node, its position, its parent (if
+/// it is not a direct child of the fragment being iterated) and its index
+/// within the parent.
+#[allow(type_alias_bounds)]
+pub type Descendant<'a, S: Schema> = (&'a S::Node, usize, Option<&'a S::Node>, usize);
+
+struct Level<'a, S: Schema> {
+    children: &'a [S::Node],
+    parent: Option<&'a S::Node>,
+    index: usize,
+    /// The position of the next child
+    pos: usize,
+}
+
+/// A lazy, depth-first iterator over the descendants of a node or fragment,
+/// restricted to the nodes that overlap a range of positions.
+///
+/// Positions are relative to the start of the fragment (or the content of the node)
+/// that the iterator was created from.
+pub struct Descendants<'a, S: Schema> {
+    stack: Vec<Level<'a, S>>,
+    /// The children of the last yielded node, to be visited next
+    pending: Option<Level<'a, S>>,
+    from: usize,
+    to: usize,
+}
+
+impl<'a, S: Schema> Descendants<'a, S> {
+    pub(crate) fn new(
+        fragment: &'a Fragment<S>,
+        parent: Option<&'a S::Node>,
+        from: usize,
+        to: usize,
+    ) -> Self {
+        Descendants {
+            stack: vec![Level {
+                children: fragment.children(),
+                parent,
+                index: 0,
+                pos: 0,
+            }],
+            pending: None,
+            from,
+            to,
+        }
+    }
+
+    /// Don't descend into the children of the node that was yielded last.
+    pub fn skip_children(&mut self) {
+        self.pending = None;
+    }
+}
+
+impl<'a, S: Schema> Iterator for Descendants<'a, S> {
+    type Item = Descendant<'a, S>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(level) = self.pending.take() {
+            self.stack.push(level);
+        }
+        loop {
+            let level = self.stack.last_mut()?;
+            let child = match level.children.get(level.index) {
+                Some(child) if level.pos < self.to => child,
+                _ => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+
+            let (pos, index, parent) = (level.pos, level.index, level.parent);
+            let end = pos + child.node_size();
+            level.index += 1;
+            level.pos = end;
+            if end > self.from {
+                self.pending =
+                    child
+                        .content()
+                        .filter(|content| content.size() > 0)
+                        .map(|content| Level {
+                            children: content.children(),
+                            parent: Some(child),
+                            index: 0,
+                            pos: pos + 1,
+                        });
+                return Some((child, pos, parent, index));
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use super::{Fragment, Node, Schema};
+
+/// The positions at which two fragments stop being equal, seen from the end.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DiffEnd {
+    /// The position in the first fragment
+    pub a: usize,
+    /// The position in the second fragment
+    pub b: usize,
+}
+
+pub(crate) fn find_diff_start<S: Schema>(
+    a: &Fragment<S>,
+    b: &Fragment<S>,
+    mut pos: usize,
+) -> Option<usize> {
+    let mut i = 0;
+    loop {
+        if i == a.child_count() || i == b.child_count() {
+            return if a.child_count() == b.child_count() {
+                None
+            } else {
+                Some(pos)
+            };
+        }
+
+        let (child_a, child_b) = (a.child(i), b.child(i));
+        if child_a == child_b {
+            pos += child_a.node_size();
+            i += 1;
+            continue;
+        }
+
+        if !child_a.same_markup(child_b) {
+            return Some(pos);
+        }
+
+        if let (Some(text_a), Some(text_b)) = (child_a.text_node(), child_b.text_node()) {
+            if text_a.text != text_b.text {
+                let chars_a = text_a.text.as_str().chars();
+                let chars_b = text_b.text.as_str().chars();
+                for (c_a, c_b) in chars_a.zip(chars_b) {
+                    if c_a != c_b {
+                        break;
+                    }
+                    pos += c_a.len_utf16();
+                }
+                return Some(pos);
+            }
+        }
+
+        if child_a.content_size() > 0 || child_b.content_size() > 0 {
+            let empty = Fragment::EMPTY_REF;
+            let content_a = child_a.content().unwrap_or(empty);
+            let content_b = child_b.content().unwrap_or(empty);
+            if let Some(inner) = find_diff_start(content_a, content_b, pos + 1) {
+                return Some(inner);
+            }
+        }
+
+        pos += child_a.node_size();
+        i += 1;
+    }
+}
+
+pub(crate) fn find_diff_end<S: Schema>(
+    a: &Fragment<S>,
+    b: &Fragment<S>,
+    mut pos_a: usize,
+    mut pos_b: usize,
+) -> Option<DiffEnd> {
+    let mut i_a = a.child_count();
+    let mut i_b = b.child_count();
+    loop {
+        if i_a == 0 || i_b == 0 {
+            return if i_a == i_b {
+                None
+            } else {
+                Some(DiffEnd { a: pos_a, b: pos_b })
+            };
+        }
+
+        i_a -= 1;
+        i_b -= 1;
+        let (child_a, child_b) = (a.child(i_a), b.child(i_b));
+        let size = child_a.node_size();
+        if child_a == child_b {
+            pos_a -= size;
+            pos_b -= size;
+            continue;
+        }
+
+        if !child_a.same_markup(child_b) {
+            return Some(DiffEnd { a: pos_a, b: pos_b });
+        }
+
+        if let (Some(text_a), Some(text_b)) = (child_a.text_node(), child_b.text_node()) {
+            if text_a.text != text_b.text {
+                let chars_a = text_a.text.as_str().chars().rev();
+                let chars_b = text_b.text.as_str().chars().rev();
+                for (c_a, c_b) in chars_a.zip(chars_b) {
+                    if c_a != c_b {
+                        break;
+                    }
+                    pos_a -= c_a.len_utf16();
+                    pos_b -= c_b.len_utf16();
+                }
+                return Some(DiffEnd { a: pos_a, b: pos_b });
+            }
+        }
+
+        if child_a.content_size() > 0 || child_b.content_size() > 0 {
+            let empty = Fragment::EMPTY_REF;
+            let content_a = child_a.content().unwrap_or(empty);
+            let content_b = child_b.content().unwrap_or(empty);
+            if let Some(inner) = find_diff_end(content_a, content_b, pos_a - 1, pos_b - 1) {
+                return Some(inner);
+            }
+        }
+
+        pos_a -= size;
+        pos_b -= size;
+    }
+}
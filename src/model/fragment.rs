@@ -1,9 +1,11 @@
-use super::{util, Index, Node, Schema, Text};
+use super::{diff, util, Descendants, DiffEnd, Index, Node, Schema};
 use derivative::Derivative;
 use displaydoc::Display;
 use serde::{Deserialize, Serialize, Serializer};
 use std::borrow::Cow;
+use std::fmt;
 use std::ops::RangeBounds;
+use std::sync::{Arc, OnceLock};
 use thiserror::Error;
 
 #[derive(Copy, Clone, Debug, Error, Display, PartialEq, Eq)]
@@ -16,18 +18,34 @@ pub enum IndexError {
 ///
 /// Like nodes, fragments are persistent data structures, and you should not mutate them or their
 /// content. Rather, you create new instances whenever needed. The API tries to make this easy.
-#[derive(Derivative, Deserialize, Eq)]
-#[derivative(Debug(bound = ""), Clone(bound = ""), PartialEq(bound = ""))]
-#[serde(from = "Vec<S::Node>")]
+///
+/// The children are reference counted, so cloning a fragment is cheap and unchanged subtrees are
+/// shared between versions of a document.
+#[derive(Derivative, Deserialize)]
+#[derivative(Clone(bound = ""))]
+#[serde(
+    from = "Vec<S::Node>",
+    bound(deserialize = "S::Node: Deserialize<'de>")
+)]
 pub struct Fragment<S: Schema> {
-    inner: Vec<S::Node>,
+    inner: Option<Arc<Children<S>>>,
     size: usize,
 }
 
+/// Fragments with at most this many children are searched linearly.
+const INDEX_THRESHOLD: usize = 8;
+
+/// The shared children of a fragment, with a lazily computed position index.
+struct Children<S: Schema> {
+    nodes: Box<[S::Node]>,
+    /// The end offset of each child, built on the first lookup in a large fragment
+    ends: OnceLock<Box<[usize]>>,
+}
+
 impl<S: Schema> Fragment<S> {
     /// An empty fragment
     pub const EMPTY: Self = Fragment {
-        inner: Vec::new(),
+        inner: None,
         size: 0,
     };
     /// Reference to an empty fragment
@@ -38,53 +56,93 @@ impl<S: Schema> Fragment<S> {
         Self::default()
     }
 
+    fn with_size(nodes: Vec<S::Node>, size: usize) -> Self {
+        let inner = if nodes.is_empty() {
+            None
+        } else {
+            Some(Arc::new(Children {
+                nodes: nodes.into(),
+                ends: OnceLock::new(),
+            }))
+        };
+        Fragment { inner, size }
+    }
+
     /// The size of the fragment, which is the total of the size of its content nodes.
     pub fn size(&self) -> usize {
         self.size
     }
 
+    /// Whether the fragment has no content, i.e. its size is zero.
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
     /// Get a slice to all child nodes
     pub fn children(&self) -> &[S::Node] {
-        &self.inner[..]
+        self.inner.as_ref().map_or(&[], |inner| &inner.nodes)
     }
 
     /// The first child of the fragment wrapped in `Some`, or `None` if it is empty.
     pub fn first_child(&self) -> Option<&S::Node> {
-        self.inner.first()
+        self.children().first()
     }
 
     /// The last child of the fragment wrapped in `Some`, or `None` if it is empty.
     pub fn last_child(&self) -> Option<&S::Node> {
-        self.inner.last()
+        self.children().last()
     }
 
     /// The number of child nodes in this fragment.
     pub fn child_count(&self) -> usize {
-        self.inner.len()
+        self.children().len()
     }
 
-    /// Create a new fragment containing the combined content of this fragment and the other.
-    pub fn append(mut self, mut other: Self) -> Self {
-        if let Some(first) = other.first_child() {
-            if let Some(last) = self.inner.last_mut() {
-                if let Some(n1) = last.text_node() {
-                    if let Some(n2) = n1.same_markup(first) {
-                        let mid = n1
-                            .with_text(Text::from(n1.text.as_str().to_owned() + n2.text.as_str()));
-                        *last = S::Node::from(mid);
-                        other.inner.remove(0);
-                    }
+    /// Whether both fragments share the same children in memory. If this is true, they are
+    /// equal.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        match (&self.inner, &other.inner) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    /// Build a fragment from an array of nodes, joining adjacent text nodes with the same marks.
+    pub fn from_array(array: Vec<S::Node>) -> Self {
+        let mut nodes: Vec<S::Node> = Vec::with_capacity(array.len());
+        for node in array {
+            if let (Some(last), Some(text)) = (nodes.last_mut(), node.text_node()) {
+                if let Some(prev) = text.same_markup(last) {
+                    *last = S::Node::from(prev.with_text(prev.text.join(&text.text)));
+                    continue;
                 }
+            }
+            nodes.push(node);
+        }
+        Fragment::from(nodes)
+    }
+
+    /// Create a new fragment containing the combined content of this fragment and the other.
+    pub fn append(self, other: Self) -> Self {
+        let (first, last) = match (other.first_child(), self.last_child()) {
+            (Some(first), Some(last)) => (first, last),
+            (Some(_), None) => return other,
+            (None, _) => return self,
+        };
 
-                self.inner.append(&mut other.inner);
-                self.size += other.size;
-                self
-            } else {
-                other
+        let mut nodes = Vec::with_capacity(self.child_count() + other.child_count());
+        nodes.extend_from_slice(self.children());
+        let mut rest = other.children();
+        if let Some(n1) = last.text_node() {
+            if let Some(n2) = n1.same_markup(first) {
+                let mid = n1.with_text(n1.text.join(&n2.text));
+                *nodes.last_mut().unwrap() = S::Node::from(mid);
+                rest = &rest[1..];
             }
-        } else {
-            self
         }
+        nodes.extend_from_slice(rest);
+        Fragment::with_size(nodes, self.size + other.size)
     }
 
     /// Cut out the sub-fragment between the two given positions.
@@ -102,7 +160,7 @@ impl<S: Schema> Fragment<S> {
             let mut pos = 0;
             let mut i = 0;
             while pos < to {
-                let child = &self.inner[i];
+                let child = self.child(i);
                 let end = pos + child.node_size();
                 if end > from {
                     let new_child = if pos < from || end > to {
@@ -128,10 +186,7 @@ impl<S: Schema> Fragment<S> {
                 i += 1;
             }
         }
-        Fragment {
-            inner: result,
-            size,
-        }
+        Fragment::with_size(result, size)
     }
 
     /// Invoke a callback for all descendant nodes between the given two positions (relative to
@@ -144,13 +199,16 @@ impl<S: Schema> Fragment<S> {
         node_start: usize,
     ) {
         let mut pos = 0;
-        for child in &self.inner {
+        for child in self.children() {
+            if pos >= to {
+                break;
+            }
             let end = pos + child.node_size();
             if end > from && f(child, node_start + pos) {
                 if let Some(content) = child.content() {
                     let start = pos + 1;
                     content.nodes_between(
-                        usize::max(0, from - start),
+                        from.saturating_sub(start),
                         usize::min(content.size(), to - start),
                         f,
                         node_start + start,
@@ -161,6 +219,18 @@ impl<S: Schema> Fragment<S> {
         }
     }
 
+    /// Iterate over all descendant nodes of this fragment, depth-first. See
+    /// [`Fragment::descendants_between`].
+    pub fn descendants(&self) -> Descendants<'_, S> {
+        Descendants::new(self, None, 0, self.size)
+    }
+
+    /// Iterate over all descendant nodes that overlap the given two positions (relative to the
+    /// start of this fragment), depth-first. The direct children of this fragment have no parent.
+    pub fn descendants_between(&self, from: usize, to: usize) -> Descendants<'_, S> {
+        Descendants::new(self, None, from, to)
+    }
+
     /// Get all text between positions from and to. When `block_separator` is given, it will be
     /// inserted whenever a new block node is started. When `leaf_text` is given, it'll be inserted
     /// for every non-text leaf node encountered.
@@ -208,33 +278,74 @@ impl<S: Schema> Fragment<S> {
 
     /// Create a new fragment in which the node at the given index is replaced by the given node.
     pub fn replace_child(&self, index: usize, node: S::Node) -> Cow<Self> {
-        let (before, rest) = self.inner.split_at(index);
+        let (before, rest) = self.children().split_at(index);
         let (current, after) = rest.split_first().unwrap();
 
         if *current == node {
             Cow::Borrowed(self)
         } else {
             let size = self.size + node.node_size() - current.node_size();
-            let mut copy = Vec::with_capacity(self.inner.capacity());
+            let mut copy = Vec::with_capacity(self.child_count());
             copy.extend_from_slice(before);
             copy.push(node);
             copy.extend_from_slice(after);
-            Cow::Owned(Fragment { inner: copy, size })
+            Cow::Owned(Fragment::with_size(copy, size))
         }
     }
 
+    /// Find the first position at which this fragment and another fragment differ, or `None`
+    /// if they are the same.
+    pub fn find_diff_start(&self, other: &Self) -> Option<usize> {
+        diff::find_diff_start(self, other, 0)
+    }
+
+    /// Find the first position, searching from the end, at which this fragment and the given
+    /// fragment differ, or `None` if they are the same. Since this position will not be the same
+    /// in both nodes, a struct with two separate positions is returned.
+    pub fn find_diff_end(&self, other: &Self) -> Option<DiffEnd> {
+        diff::find_diff_end(self, other, self.size, other.size)
+    }
+
     /// Get the child node at the given index. Panics when the index is out of range.
     pub fn child(&self, index: usize) -> &S::Node {
-        &self.inner[index]
+        &self.children()[index]
     }
 
     /// Get the child node at the given index, if it exists.
     pub fn maybe_child(&self, index: usize) -> Option<&S::Node> {
-        self.inner.get(index)
+        self.children().get(index)
+    }
+
+    /// Write the children, separated by commas
+    pub(crate) fn fmt_children(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, child) in self.children().iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", child)?;
+        }
+        Ok(())
+    }
+
+    /// The end offsets of the children, if the fragment is large enough to be worth indexing
+    fn ends(&self) -> Option<&[usize]> {
+        let inner = self
+            .inner
+            .as_ref()
+            .filter(|inner| inner.nodes.len() > INDEX_THRESHOLD)?;
+        let ends = inner.ends.get_or_init(|| {
+            let mut pos = 0;
+            let ends = inner.nodes.iter().map(|node| {
+                pos += node.node_size();
+                pos
+            });
+            ends.collect()
+        });
+        Some(ends)
     }
 
     pub(crate) fn find_index(&self, pos: usize, round: bool) -> Result<Index, IndexError> {
-        let len = self.inner.len();
+        let len = self.child_count();
         match pos {
             0 => Ok(Index {
                 index: 0,
@@ -246,25 +357,39 @@ impl<S: Schema> Fragment<S> {
             }),
             p if p > self.size => Err(IndexError::OutOfBounds(p)),
             p => {
-                let mut cur_pos = 0;
-                for (i, cur) in self.inner.iter().enumerate() {
-                    let end = cur_pos + cur.node_size();
-                    if end >= p {
-                        if (end == p) || round {
-                            return Ok(Index {
-                                index: i + 1,
-                                offset: end,
-                            });
-                        } else {
-                            return Ok(Index {
-                                index: i,
-                                offset: cur_pos,
-                            });
+                let (index, start, end) = match self.ends() {
+                    Some(ends) => {
+                        // The first child that ends at or after `p`
+                        let i = ends.partition_point(|&end| end < p);
+                        let start = if i == 0 { 0 } else { ends[i - 1] };
+                        (i, start, ends[i])
+                    }
+                    None => {
+                        let mut cur_pos = 0;
+                        let mut found = None;
+                        for (i, cur) in self.children().iter().enumerate() {
+                            let end = cur_pos + cur.node_size();
+                            if end >= p {
+                                found = Some((i, cur_pos, end));
+                                break;
+                            }
+                            cur_pos = end;
                         }
+                        found
+                            .expect("Invariant failed: self.size must be the sum of all node sizes")
                     }
-                    cur_pos = end;
+                };
+                if (end == p) || round {
+                    Ok(Index {
+                        index: index + 1,
+                        offset: end,
+                    })
+                } else {
+                    Ok(Index {
+                        index,
+                        offset: start,
+                    })
                 }
-                panic!("Invariant failed: self.size must be the sum of all node sizes")
             }
         }
     }
@@ -272,10 +397,30 @@ impl<S: Schema> Fragment<S> {
 
 impl<S: Schema> Default for Fragment<S> {
     fn default() -> Self {
-        Self {
-            inner: Vec::new(),
-            size: 0,
-        }
+        Self::EMPTY
+    }
+}
+
+impl<S: Schema> PartialEq for Fragment<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr_eq(other) || (self.size == other.size && self.children() == other.children())
+    }
+}
+
+impl<S: Schema> Eq for Fragment<S> {}
+
+/// Prints the fragment in the notation of ProseMirror's `toString`, e.g. `<paragraph("a")>`.
+impl<S: Schema> fmt::Display for Fragment<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<")?;
+        self.fmt_children(f)?;
+        write!(f, ">")
+    }
+}
+
+impl<S: Schema> fmt::Debug for Fragment<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
     }
 }
 
@@ -284,20 +429,20 @@ impl<S: Schema> Serialize for Fragment<S> {
     where
         Sr: Serializer,
     {
-        self.inner.serialize(serializer)
+        self.children().serialize(serializer)
     }
 }
 
 impl<S: Schema> From<Vec<S::Node>> for Fragment<S> {
     fn from(src: Vec<S::Node>) -> Fragment<S> {
         let size = src.iter().map(|x| x.node_size()).sum::<usize>();
-        Fragment { inner: src, size }
+        Fragment::with_size(src, size)
     }
 }
 
 impl<S: Schema> From<Fragment<S>> for Vec<S::Node> {
     fn from(src: Fragment<S>) -> Vec<S::Node> {
-        src.inner
+        src.children().to_vec()
     }
 }
 
@@ -1,4 +1,4 @@
-use super::Schema;
+use super::{AttrError, Schema};
 use derivative::Derivative;
 use displaydoc::Display;
 use serde::{Deserialize, Serialize, Serializer};
@@ -19,11 +19,24 @@ pub struct MarkSet<S: Schema> {
 }
 
 impl<S: Schema> MarkSet<S> {
+    /// Whether the set contains no marks
+    pub fn is_empty(&self) -> bool {
+        self.content.is_empty()
+    }
+
     /// Check whether the set contains this exact mark
     pub fn contains(&self, mark: &S::Mark) -> bool {
         self.content.contains(mark)
     }
 
+    /// Find the mark of the given type in this set, if any
+    pub fn find(&self, mark_type: S::MarkType) -> Option<&S::Mark> {
+        self.content
+            .binary_search_by_key(&mark_type, Mark::r#type)
+            .ok()
+            .map(|index| &self.content[index])
+    }
+
     /// Add a mark to the set
     pub fn add(&mut self, mark: &S::Mark) {
         match self
@@ -105,6 +118,11 @@ pub trait Mark<S: Schema<Mark = Self>>:
     /// The type of this mark.
     fn r#type(&self) -> S::MarkType;
 
+    /// Check whether the attributes of this mark conform to the spec of its type.
+    fn validate_attrs(&self) -> Result<(), AttrError> {
+        Ok(())
+    }
+
     /// Given a set of marks, create a new set which contains this one as well, in the right
     /// position. If this mark is already in the set, the set itself is returned. If any marks that
     /// are set to be exclusive with this mark are present, those are replaced by this one.
@@ -137,12 +155,12 @@ pub trait Mark<S: Schema<Mark = Self>>:
             .content
             .binary_search_by_key(&self.r#type(), Mark::r#type)
         {
-            Ok(index) => {
+            Ok(index) if &set.content[index] == self => {
                 let mut owned_set = set.into_owned();
                 owned_set.content.remove(index);
                 Cow::Owned(owned_set)
             }
-            Err(_index) => set,
+            _ => set,
         }
     }
 
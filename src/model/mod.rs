@@ -2,30 +2,58 @@
 //!
 //! This module is derived from the `prosemirror-markdown` schema and the
 //! the general JSON serialization of nodes.
+mod attrs;
+mod builder;
 mod content;
+mod descendants;
+mod diff;
 mod fragment;
 mod marks;
 mod node;
 mod replace;
 mod resolved_pos;
 mod schema;
+mod text;
 pub(crate) mod util;
 
+pub use attrs::{AttrError, AttrSpec};
+pub use builder::{Tagged, TaggedNode};
 pub use content::{ContentMatch, ContentMatchError};
+pub use descendants::{Descendant, Descendants};
+pub use diff::DiffEnd;
 pub use fragment::Fragment;
 pub use marks::{Mark, MarkSet};
-pub use node::{Node, NodeType, SliceError, Text};
+pub use node::{CheckError, CheckErrorKind, Node, NodeType, SliceError};
 pub use replace::{InsertError, ReplaceError, Slice};
 pub use resolved_pos::{ResolveErr, ResolvedNode, ResolvedPos};
 pub use schema::{AttrNode, Block, Leaf, MarkType, Schema, TextNode};
+pub use text::Text;
 
 pub(crate) use replace::replace;
 pub(crate) use resolved_pos::Index;
 
+#[allow(dead_code)]
+fn assert_send_sync<S: Schema>() {
+    use crate::util::assert_send_sync;
+    assert_send_sync::<S::Node>();
+    assert_send_sync::<S::Mark>();
+    assert_send_sync::<Fragment<S>>();
+    assert_send_sync::<MarkSet<S>>();
+    assert_send_sync::<Slice<S>>();
+    assert_send_sync::<Text>();
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{fragment::IndexError, Index, Node, ResolvedNode, ResolvedPos};
-    use crate::markdown::{helper::*, ImageAttrs, MarkdownNode, MD};
+    use super::{
+        fragment::IndexError, AttrError, CheckError, CheckErrorKind, DiffEnd, Fragment, Index,
+        Mark, MarkSet, Node, ResolvedNode, ResolvedPos, TaggedNode, TextNode,
+    };
+    use crate::markdown::{
+        helper::*, ImageAttrs, LinkAttrs, MarkdownMark, MarkdownMarkType, MarkdownNode,
+        MarkdownNodeType, MD,
+    };
+    use crate::transform::Step;
     use std::fmt::Debug;
     use std::ops::Deref;
 
@@ -41,6 +69,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_serialize_like_to_json() {
+        let node = doc(vec![
+            p(img!(src = "a.png").node),
+            p(vec![node("x"), strong("y")]),
+            p(vec![]),
+        ]);
+        assert_eq!(
+            serde_json::to_string(&node).unwrap(),
+            concat!(
+                r#"{"type":"doc","content":["#,
+                r#"{"type":"paragraph","content":[{"type":"image","attrs":{"src":"a.png","alt":null,"title":null}}]},"#,
+                r#"{"type":"paragraph","content":[{"type":"text","text":"x"},{"type":"text","marks":[{"type":"strong"}],"text":"y"}]},"#,
+                r#"{"type":"paragraph"}]}"#
+            )
+        );
+    }
+
     #[test]
     fn test_deserialize_text() {
         assert_eq!(
@@ -49,6 +95,394 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_attr_defaults() {
+        assert_eq!(
+            serde_json::from_str::<MarkdownNode>(r#"{"type": "heading"}"#).unwrap(),
+            h1(Vec::new()),
+        );
+        assert_eq!(
+            serde_json::from_str::<MarkdownNode>(
+                r#"{"type": "code_block", "attrs": {"params": null}}"#
+            )
+            .unwrap(),
+            code_block("", Vec::new()),
+        );
+    }
+
+    #[test]
+    fn test_attr_validation() {
+        let err = serde_json::from_str::<MarkdownNode>(
+            r#"{"type": "heading", "attrs": {"level": 42}, "content": []}"#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Attribute `level` must be between 1 and 6, got 42"
+        );
+
+        let step = r#"{"stepType":"replace","from":0,"to":0,"slice":{"content":[
+            {"type":"heading","attrs":{"level":0}}
+        ]}}"#;
+        assert!(serde_json::from_str::<Step<MD>>(step).is_err());
+
+        let err = serde_json::from_str::<MarkdownNode>(
+            r#"{"type": "code_block", "attrs": {"params": "rust\nfn"}}"#,
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "Attribute `params` may not be `rust\nfn`");
+
+        assert_eq!(
+            doc(code_block("a\nb", "")).check().unwrap_err().kind,
+            CheckErrorKind::Attrs(AttrError::NotAllowed {
+                name: "params",
+                value: "a\nb".into()
+            })
+        );
+    }
+
+    #[test]
+    fn test_can_replace() {
+        let code = code_block("", "foo");
+        let text = Fragment::from(vec![node("x")]);
+        assert!(code.can_replace(1, 1, Some(&text), ..).unwrap());
+        let marked = Fragment::from(vec![strong("x")]);
+        assert!(!code.can_replace(1, 1, Some(&marked), ..).unwrap());
+
+        let para = p("foo");
+        assert!(para.can_replace(1, 1, Some(&marked), ..).unwrap());
+    }
+
+    #[test]
+    fn test_check() {
+        let valid = doc(vec![
+            h1("Title"),
+            p(vec![node("Hello "), strong("World")]),
+            blockquote(ul(li(p("item")))),
+        ]);
+        assert_eq!(valid.check(), Ok(()));
+
+        let code = doc(code_block("", "fn main() {}"));
+        assert_eq!(code.check(), Ok(()));
+
+        let empty_text = doc(vec![p("one"), blockquote(p(""))]);
+        assert_eq!(
+            empty_text.check(),
+            Err(CheckError {
+                path: vec![1, 0, 0],
+                kind: CheckErrorKind::EmptyText
+            })
+        );
+
+        let unjoined = doc(p(vec![node("Hello "), node("World")]));
+        let err = unjoined.check().unwrap_err();
+        assert_eq!(err.path, vec![0, 1]);
+        assert_eq!(err.kind, CheckErrorKind::UnjoinedText);
+        assert_eq!(
+            err.to_string(),
+            "Invalid node at [0, 1]: Adjacent text nodes with the same marks must be joined"
+        );
+
+        let bad_content = doc(blockquote("text"));
+        assert_eq!(
+            bad_content.check(),
+            Err(CheckError {
+                path: vec![0],
+                kind: CheckErrorKind::InvalidContent(MarkdownNodeType::Blockquote)
+            })
+        );
+
+        let bad_mark = doc(code_block("rust", strong("fn main() {}")));
+        assert_eq!(
+            bad_mark.check(),
+            Err(CheckError {
+                path: vec![0, 0],
+                kind: CheckErrorKind::MarkNotAllowed(
+                    MarkdownNodeType::CodeBlock,
+                    MarkdownMarkType::Strong
+                )
+            })
+        );
+
+        let bad_attrs = doc(vec![p("a"), h(7, "Too deep")]);
+        assert_eq!(
+            bad_attrs.check(),
+            Err(CheckError {
+                path: vec![1],
+                kind: CheckErrorKind::Attrs(AttrError::OutOfRange {
+                    name: "level",
+                    value: 7,
+                    min: 1,
+                    max: 6
+                })
+            })
+        );
+    }
+
+    fn diff_start(a: MarkdownNode, b: MarkdownNode) -> Option<usize> {
+        a.content().unwrap().find_diff_start(b.content().unwrap())
+    }
+
+    fn diff_end(a: MarkdownNode, b: MarkdownNode) -> Option<usize> {
+        let found = a.content().unwrap().find_diff_end(b.content().unwrap());
+        found.map(|d| d.a)
+    }
+
+    fn diff_doc() -> Vec<MarkdownNode> {
+        vec![
+            p(vec![node("a"), em("b")]),
+            p("hello"),
+            blockquote(h1("bye")),
+        ]
+    }
+
+    #[test]
+    fn test_find_diff_start() {
+        // returns null for identical nodes
+        assert_eq!(diff_start(doc(diff_doc()), doc(diff_doc())), None);
+
+        // notices when one node is longer
+        let mut longer = diff_doc();
+        longer.push(p("oops"));
+        assert_eq!(diff_start(doc(diff_doc()), doc(longer.clone())), Some(18));
+
+        // notices when one node is shorter
+        assert_eq!(diff_start(doc(longer), doc(diff_doc())), Some(18));
+
+        // notices differing marks
+        let (a, b) = (
+            doc(p(vec![node("a"), em("b")])),
+            doc(p(vec![node("a"), strong("b")])),
+        );
+        assert_eq!(diff_start(a, b), Some(2));
+
+        // stops at longer text
+        let (a, b) = (
+            doc(p(vec![node("foobar"), em("b")])),
+            doc(p(vec![node("foo"), em("b")])),
+        );
+        assert_eq!(diff_start(a, b), Some(4));
+
+        // stops at a different character
+        assert_eq!(diff_start(doc(p("foobar")), doc(p("foocar"))), Some(4));
+
+        // stops at a different node type
+        let (a, b) = (doc(vec![p("a"), p("b")]), doc(vec![p("a"), h1("b")]));
+        assert_eq!(diff_start(a, b), Some(3));
+
+        // works when the difference is at the start
+        assert_eq!(diff_start(doc(p("b")), doc(h1("b"))), Some(0));
+
+        // notices a different attribute
+        let (a, b) = (doc(vec![p("a"), h1("foo")]), doc(vec![p("a"), h2("foo")]));
+        assert_eq!(diff_start(a, b), Some(3));
+
+        // counts positions in utf-16
+        let (a, b) = (doc(p("a\u{1F60A}b")), doc(p("a\u{1F60A}c")));
+        assert_eq!(diff_start(a, b), Some(4));
+    }
+
+    #[test]
+    fn test_find_diff_end() {
+        // returns null when there is no difference
+        assert_eq!(diff_end(doc(diff_doc()), doc(diff_doc())), None);
+
+        // notices when the second doc is longer
+        let mut longer = vec![p("oops")];
+        longer.extend(diff_doc());
+        assert_eq!(diff_end(doc(diff_doc()), doc(longer.clone())), Some(0));
+
+        // notices when the second doc is shorter
+        assert_eq!(diff_end(doc(longer), doc(diff_doc())), Some(6));
+
+        // notices different styles
+        let a = doc(p(vec![node("a"), em("b"), node("c")]));
+        let b = doc(p(vec![node("a"), strong("b"), node("c")]));
+        assert_eq!(diff_end(a, b), Some(3));
+
+        // spots longer text
+        let (a, b) = (
+            doc(p(vec![node("barfoo"), em("b")])),
+            doc(p(vec![node("foo"), em("b")])),
+        );
+        assert_eq!(diff_end(a, b), Some(4));
+
+        // spots different text
+        assert_eq!(diff_end(doc(p("foobar")), doc(p("foocar"))), Some(5));
+
+        // notices different nodes
+        let (a, b) = (doc(vec![p("a"), p("b")]), doc(vec![p("a"), h1("b")]));
+        assert_eq!(diff_end(a, b), Some(6));
+
+        // notices a difference at the start
+        assert_eq!(diff_end(doc(p("b")), doc(h1("b"))), Some(3));
+
+        // handles a similar start
+        assert_eq!(
+            diff_end(doc(p("hello")), doc(vec![p("hey"), p("hello")])),
+            Some(0)
+        );
+
+        // counts positions in utf-16
+        let a = doc(p("\u{1F60A}x"));
+        let b = doc(p("y\u{1F60A}x"));
+        let found = a.content().unwrap().find_diff_end(b.content().unwrap());
+        assert_eq!(found, Some(DiffEnd { a: 1, b: 2 }));
+    }
+
+    #[test]
+    fn test_descendants() {
+        let test_doc = doc(vec![
+            h1("Title"),
+            p(("ab", em("cd"))),
+            blockquote((p(("x",)),)),
+        ]);
+        let (h, para, quote) = (
+            test_doc.child(0).unwrap(),
+            test_doc.child(1).unwrap(),
+            test_doc.child(2).unwrap(),
+        );
+
+        let all: Vec<_> = test_doc
+            .descendants()
+            .map(|(node, pos, parent, index)| (node.r#type(), pos, parent.map(Node::r#type), index))
+            .collect();
+        let (d, t) = (Some(MarkdownNodeType::Doc), MarkdownNodeType::Text);
+        assert_eq!(
+            all,
+            vec![
+                (MarkdownNodeType::Heading, 0, d, 0),
+                (t, 1, Some(MarkdownNodeType::Heading), 0),
+                (MarkdownNodeType::Paragraph, 7, d, 1),
+                (t, 8, Some(MarkdownNodeType::Paragraph), 0),
+                (t, 10, Some(MarkdownNodeType::Paragraph), 1),
+                (MarkdownNodeType::Blockquote, 13, d, 2),
+                (
+                    MarkdownNodeType::Paragraph,
+                    14,
+                    Some(MarkdownNodeType::Blockquote),
+                    0
+                ),
+                (t, 15, Some(MarkdownNodeType::Paragraph), 0),
+            ]
+        );
+
+        let headings: Vec<_> = test_doc
+            .descendants()
+            .filter(|(node, ..)| node.r#type() == MarkdownNodeType::Heading)
+            .map(|(node, ..)| node.text_content())
+            .collect();
+        assert_eq!(headings, vec!["Title"]);
+
+        let between: Vec<_> = test_doc
+            .descendants_between(9, 10)
+            .map(|(node, pos, ..)| (node, pos))
+            .collect();
+        assert_eq!(between, vec![(para, 7), (para.child(0).unwrap(), 8)]);
+
+        let fragment = test_doc.content().unwrap();
+        let top: Vec<_> = fragment
+            .descendants()
+            .map(|(_, pos, parent, _)| (pos, parent))
+            .collect();
+        assert_eq!(top[0], (0, None));
+
+        let mut iter = test_doc.descendants();
+        let mut outer = vec![];
+        while let Some((node, ..)) = iter.next() {
+            iter.skip_children();
+            outer.push(node);
+        }
+        assert_eq!(outer, vec![h, para, quote]);
+
+        let mut visited = vec![];
+        test_doc.nodes_between(0, test_doc.content_size(), |node, pos, _, _| {
+            visited.push(pos);
+            node != quote
+        });
+        assert_eq!(visited, vec![0, 1, 7, 8, 10, 13]);
+
+        let mut visited = vec![];
+        fragment.nodes_between(
+            0,
+            9,
+            &mut |_, pos| {
+                visited.push(pos);
+                true
+            },
+            0,
+        );
+        assert_eq!(visited, vec![0, 1, 7, 8]);
+    }
+
+    fn marked(text: &str, marks: &[MarkdownMark]) -> MarkdownNode {
+        let mut set = MarkSet::default();
+        for mark in marks {
+            set.add(mark);
+        }
+        MarkdownNode::Text(TextNode {
+            text: text.to_owned().into(),
+            marks: set,
+        })
+    }
+
+    fn link(href: &str) -> MarkdownMark {
+        MarkdownMark::Link {
+            attrs: LinkAttrs {
+                href: href.to_owned(),
+                title: String::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_marks_in_range() {
+        let strong_link = [link("u"), MarkdownMark::Strong];
+        let test_doc = doc(vec![
+            p(vec![node("a "), strong("bc"), node(" d")]),
+            p(vec![
+                marked("xy", &[link("u")]),
+                marked("zz", &strong_link),
+                marked("w", &[link("v")]),
+            ]),
+        ]);
+        let size = test_doc.content_size();
+
+        assert!(test_doc.range_has_mark(0, size, MarkdownMarkType::Strong));
+        assert!(!test_doc.range_has_mark(1, 3, MarkdownMarkType::Strong));
+        assert!(test_doc.range_has_mark(2, 4, MarkdownMarkType::Strong));
+        assert!(!test_doc.range_has_mark(5, 7, MarkdownMarkType::Strong));
+        assert!(!test_doc.range_has_mark(3, 3, MarkdownMarkType::Strong));
+        assert!(!test_doc.range_has_mark(0, 8, MarkdownMarkType::Link));
+        assert!(test_doc.range_has_mark(0, size, MarkdownMarkType::Link));
+
+        assert_eq!(
+            test_doc.marks_in_range(0, size),
+            vec![
+                (MarkdownMark::Strong, 3..5),
+                (link("u"), 9..13),
+                (MarkdownMark::Strong, 11..13),
+                (link("v"), 13..14),
+            ]
+        );
+        assert_eq!(
+            test_doc.marks_in_range(4, 10),
+            vec![(MarkdownMark::Strong, 4..5), (link("u"), 9..10)]
+        );
+
+        let links: Vec<_> = test_doc
+            .marks_in_range(0, size)
+            .into_iter()
+            .filter(|(mark, _)| mark.r#type() == MarkdownMarkType::Link)
+            .collect();
+        assert_eq!(links.len(), 2);
+
+        let zz = test_doc.child(1).unwrap().child(1).unwrap();
+        let marks = zz.marks().unwrap();
+        assert_eq!(marks.find(MarkdownMarkType::Link), Some(&link("u")));
+        assert_eq!(marks.find(MarkdownMarkType::Em), None);
+    }
+
     #[test]
     fn test_size() {
         assert_eq!(node("Hello").node_size(), 5);
@@ -78,6 +512,45 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_find_index_large() {
+        // Large fragments are searched through an index, which must agree with a linear scan
+        let words = ["a", "bc", "def", "ghij"];
+        let test_doc = doc((0..50).map(|i| p(words[i % 4])).collect::<Vec<_>>());
+        let content = test_doc.content().unwrap();
+        for pos in 0..=content.size() {
+            for &round in &[false, true] {
+                let mut expected = Index::new(content.child_count(), pos);
+                let mut start = 0;
+                for (i, child) in content.children().iter().enumerate() {
+                    let end = start + child.node_size();
+                    if pos == start {
+                        expected = Index::new(i, start);
+                        break;
+                    } else if pos < end {
+                        expected = match round {
+                            true => Index::new(i + 1, end),
+                            false => Index::new(i, start),
+                        };
+                        break;
+                    }
+                    start = end;
+                }
+                assert_eq!(content.find_index(pos, round), Ok(expected), "{}", pos);
+            }
+        }
+        let size = content.size();
+        assert_eq!(
+            content.find_index(size + 1, false),
+            Err(IndexError::OutOfBounds(size + 1))
+        );
+
+        let pos = ResolvedPos::<MD>::resolve(&test_doc, 100).unwrap();
+        assert_eq!(pos.depth, 1);
+        assert_eq!(pos.index(0), 22);
+        assert_eq!(pos.parent_offset, 2);
+    }
+
     #[derive(Debug, Copy, Clone, PartialEq, Eq)]
     struct Sol<'a> {
         node: &'a MarkdownNode,
@@ -151,4 +624,151 @@ mod tests {
             assert_eq!(pos.node_after(), *after);
         }
     }
+
+    #[test]
+    fn test_pos_at_index() {
+        let d = doc!(blockquote!(
+            p!("one"),
+            blockquote!(p!("two ", em!("three")), p!("four"))
+        ));
+        let p_three = d.resolve(12).unwrap(); // Start of em("three")
+        let depth = p_three.depth;
+        assert_eq!(p_three.pos_at_index(0, depth), 8);
+        assert_eq!(p_three.pos_at_index(1, depth), 12);
+        assert_eq!(p_three.pos_at_index(2, depth), 17);
+        assert_eq!(p_three.pos_at_index(0, 2), 7);
+        assert_eq!(p_three.pos_at_index(1, 2), 18);
+        assert_eq!(p_three.pos_at_index(2, 2), 24);
+        assert_eq!(p_three.pos_at_index(0, 1), 1);
+        assert_eq!(p_three.pos_at_index(1, 1), 6);
+        assert_eq!(p_three.pos_at_index(2, 1), 25);
+        assert_eq!(p_three.pos_at_index(0, 0), 0);
+        assert_eq!(p_three.pos_at_index(1, 0), 26);
+    }
+
+    // The `test-mark` suite of prosemirror-model
+
+    fn titled_link(href: &str, title: &str) -> MarkdownMark {
+        MarkdownMark::Link {
+            attrs: LinkAttrs {
+                href: href.to_owned(),
+                title: title.to_owned(),
+            },
+        }
+    }
+
+    fn set_of(marks: &[MarkdownMark]) -> MarkSet<MD> {
+        let mut set = MarkSet::default();
+        for mark in marks {
+            set.add(mark);
+        }
+        set
+    }
+
+    fn in_order(set: &MarkSet<MD>) -> Vec<MarkdownMark> {
+        set.into_iter().cloned().collect()
+    }
+
+    #[test]
+    fn test_mark_same_set() {
+        use MarkdownMark::{Code, Em, Strong};
+        assert_eq!(set_of(&[]), set_of(&[]));
+        assert_eq!(set_of(&[Em, Strong]), set_of(&[Em, Strong]));
+        assert_ne!(set_of(&[Em, Strong]), set_of(&[Em, Code]));
+        assert_ne!(set_of(&[Em, Strong]), set_of(&[Em, Strong, Code]));
+        assert_eq!(
+            set_of(&[link("http://foo"), Code]),
+            set_of(&[link("http://foo"), Code])
+        );
+        assert_ne!(
+            set_of(&[link("http://foo"), Code]),
+            set_of(&[link("http://bar"), Code])
+        );
+    }
+
+    #[test]
+    fn test_mark_eq() {
+        assert_eq!(link("http://foo"), link("http://foo"));
+        assert_ne!(link("http://foo"), link("http://bar"));
+        assert_ne!(
+            titled_link("http://foo", "A"),
+            titled_link("http://foo", "B")
+        );
+    }
+
+    #[test]
+    fn test_mark_add_to_set() {
+        use std::borrow::Cow;
+        use MarkdownMark::{Code, Em, Strong};
+        let add = |mark: MarkdownMark, set: &[MarkdownMark]| {
+            in_order(&mark.add_to_set(Cow::Owned(set_of(set))))
+        };
+
+        assert_eq!(add(Em, &[]), [Em]);
+        assert_eq!(add(Em, &[Em]), [Em]);
+        assert_eq!(add(Em, &[Strong]), [Em, Strong]);
+        assert_eq!(add(Strong, &[Em]), [Em, Strong]);
+        assert_eq!(
+            add(link("http://bar"), &[link("http://foo"), Em]),
+            [Em, link("http://bar")]
+        );
+        assert_eq!(
+            add(link("http://foo"), &[Em, link("http://foo")]),
+            [Em, link("http://foo")]
+        );
+        assert_eq!(
+            add(Code, &[Em, Strong, link("http://foo")]),
+            [Em, Strong, link("http://foo"), Code]
+        );
+        assert_eq!(add(Strong, &[Em, Code]), [Em, Strong, Code]);
+    }
+
+    #[test]
+    fn test_mark_remove_from_set() {
+        use std::borrow::Cow;
+        use MarkdownMark::{Em, Strong};
+        let remove = |mark: MarkdownMark, set: &[MarkdownMark]| {
+            in_order(&mark.remove_from_set(Cow::Owned(set_of(set))))
+        };
+
+        assert_eq!(remove(Em, &[]), []);
+        assert_eq!(remove(Em, &[Em]), []);
+        assert_eq!(remove(Strong, &[Em]), [Em]);
+        assert_eq!(remove(link("http://foo"), &[link("http://foo")]), []);
+        assert_eq!(
+            remove(titled_link("http://foo", "title"), &[link("http://foo")]),
+            [link("http://foo")]
+        );
+    }
+
+    #[test]
+    fn test_resolved_pos_marks() {
+        fn is_at(d: TaggedNode<MD>, mark: &MarkdownMark) -> bool {
+            d.resolve(d.tag("a")).unwrap().marks().contains(mark)
+        }
+        use MarkdownMark::{Em, Strong};
+
+        assert!(is_at(doc!(p!(em!("fo<a>o"))), &Em));
+        assert!(!is_at(doc!(p!(em!("fo<a>o"))), &Strong));
+        assert!(is_at(doc!(p!(em!("hi"), "<a> there")), &Em));
+        assert!(!is_at(doc!(p!("one <a>", em!("two"))), &Em));
+        assert!(is_at(doc!(p!(em!("<a>one"))), &Em));
+        assert!(!is_at(
+            doc!(p!(a!(href = "http://foo"; "li<a>nk"))),
+            &link("http://baz")
+        ));
+        // Links are not inclusive
+        assert!(!is_at(
+            doc!(p!(a!(href = "http://foo"; "link"), "<a> after")),
+            &link("http://foo")
+        ));
+        assert!(is_at(
+            doc!(p!(
+                a!(href = "http://foo"; "one"),
+                "<a>",
+                a!(href = "http://foo"; "two")
+            )),
+            &link("http://foo")
+        ));
+    }
 }
@@ -1,12 +1,14 @@
 use super::{
-    replace, util, ContentMatch, ContentMatchError, Fragment, MarkSet, ReplaceError, ResolveErr,
-    ResolvedPos, Schema, Slice, TextNode,
+    replace, util, AttrError, ContentMatch, ContentMatchError, Descendants, Fragment, Mark,
+    MarkSet, ReplaceError, ResolveErr, ResolvedPos, Schema, Slice, Text, TextNode,
 };
+use derivative::Derivative;
 use displaydoc::Display;
-use serde::{Deserialize, Serialize, Serializer};
+use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
-use std::fmt::Debug;
-use std::ops::RangeBounds;
+use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+use std::ops::{Range, RangeBounds};
 use thiserror::Error;
 
 #[derive(Debug, Clone, Error, Display, Eq, PartialEq)]
@@ -18,8 +20,60 @@ pub enum SliceError {
     Unknown,
 }
 
+/// Invalid node at {path:?}: {kind}
+#[derive(Derivative, Display, Error)]
+#[derivative(
+    Clone(bound = ""),
+    Debug(bound = ""),
+    PartialEq(bound = ""),
+    Eq(bound = "")
+)]
+pub struct CheckError<S: Schema> {
+    /// The child indices that lead from the checked node to the offending node
+    pub path: Vec<usize>,
+    /// What is wrong with the node
+    pub kind: CheckErrorKind<S>,
+}
+
+impl<S: Schema> CheckError<S> {
+    fn new(kind: CheckErrorKind<S>) -> Self {
+        Self { path: vec![], kind }
+    }
+
+    fn at(index: usize, kind: CheckErrorKind<S>) -> Self {
+        Self {
+            path: vec![index],
+            kind,
+        }
+    }
+}
+
+/// The different ways a node can be invalid
+#[derive(Derivative, Display)]
+#[derivative(
+    Clone(bound = ""),
+    Debug(bound = ""),
+    PartialEq(bound = ""),
+    Eq(bound = "")
+)]
+pub enum CheckErrorKind<S: Schema> {
+    /// Invalid content for node {0:?}
+    InvalidContent(S::NodeType),
+    /// Mark {1:?} is not allowed in node {0:?}
+    MarkNotAllowed(S::NodeType, S::MarkType),
+    /// Invalid attributes: {0}
+    Attrs(AttrError),
+    /// Empty text nodes are not allowed
+    EmptyText,
+    /// Adjacent text nodes with the same marks must be joined
+    UnjoinedText,
+}
+
 /// This is the type that encodes a kind of node
 pub trait NodeType<S: Schema>: Copy + Clone + Debug + PartialEq + Eq {
+    /// The name of this node type in the schema, e.g. `paragraph`.
+    fn name(self) -> &'static str;
+
     /// ???
     fn compatible_content(self, other: Self) -> bool;
     /// ???
@@ -48,7 +102,15 @@ pub trait NodeType<S: Schema>: Copy + Clone + Debug + PartialEq + Eq {
 /// This class represents a node in the tree that makes up a ProseMirror document. So a document is
 /// an instance of Node, with children that are also instances of Node.
 pub trait Node<S: Schema<Node = Self> + 'static>:
-    Serialize + for<'de> Deserialize<'de> + Clone + Debug + PartialEq + Eq + Sized + From<TextNode<S>>
+    Serialize
+    + for<'de> Deserialize<'de>
+    + Clone
+    + Debug
+    + Display
+    + PartialEq
+    + Eq
+    + Sized
+    + From<TextNode<S>>
 {
     /// The size of this node, as defined by the integer-based indexing scheme. For text nodes,
     /// this is the amount of characters. For other leaf nodes, it is one. For non-leaf nodes, it
@@ -58,7 +120,7 @@ pub trait Node<S: Schema<Node = Self> + 'static>:
             Some(c) => c.size() + 2,
             None => {
                 if let Some(node) = self.text_node() {
-                    node.text.len_utf16
+                    node.text.len_utf16()
                 } else {
                     1
                 }
@@ -89,13 +151,13 @@ pub trait Node<S: Schema<Node = Self> + 'static>:
         let from = util::from(&range);
 
         if let Some(TextNode { text, marks }) = self.text_node() {
-            let len = text.len_utf16;
+            let len = text.len_utf16();
             let to = util::to(&range, len);
 
             if from == 0 && to == len {
                 return Cow::Borrowed(self);
             }
-            let (_, rest) = util::split_at_utf16(&text.content, from);
+            let (_, rest) = util::split_at_utf16(text.as_str(), from);
             let (rest, _) = util::split_at_utf16(rest, to - from);
 
             Cow::Owned(Self::new_text_node(TextNode {
@@ -121,7 +183,7 @@ pub trait Node<S: Schema<Node = Self> + 'static>:
         include_parents: bool,
     ) -> Result<Slice<S>, SliceError> {
         let from = util::from(&range);
-        let to = util::to(&range, self.node_size());
+        let to = util::to(&range, self.content_size());
 
         if from == to {
             return Ok(Slice::default());
@@ -129,24 +191,7 @@ pub trait Node<S: Schema<Node = Self> + 'static>:
 
         let rp_from = self.resolve(from)?;
         let rp_to = self.resolve(to)?;
-
-        let depth = if include_parents {
-            0
-        } else {
-            rp_from.shared_depth(to)
-        };
-
-        let (start, node) = (rp_from.start(depth), rp_from.node(depth));
-        let content = if let Some(c) = node.content() {
-            c.cut(rp_from.pos - start..rp_to.pos - start)
-        } else {
-            Fragment::new()
-        };
-        Ok(Slice::new(
-            content,
-            rp_from.depth - depth,
-            rp_to.depth - depth,
-        ))
+        Ok(Slice::between(&rp_from, &rp_to, include_parents))
     }
 
     /// Replace the part of the document between the given positions with the given slice. The
@@ -160,8 +205,7 @@ pub trait Node<S: Schema<Node = Self> + 'static>:
         slice: &Slice<S>,
     ) -> Result<Self, ReplaceError<S>> {
         let from = util::from(&range);
-        let to = util::to(&range, self.node_size());
-        // FIXME: this max value is my guess, that needs to be tested out
+        let to = util::to(&range, self.content_size());
 
         assert!(to >= from, "replace: {} >= {}", to, from);
 
@@ -184,10 +228,78 @@ pub trait Node<S: Schema<Node = Self> + 'static>:
     where
         F: FnOnce(&Fragment<S>) -> Fragment<S>;
 
+    /// Iterate over all descendant nodes of this node, depth-first. Positions are relative to
+    /// the start of this node's content.
+    fn descendants(&self) -> Descendants<'_, S> {
+        self.descendants_between(0, self.content_size())
+    }
+
+    /// Iterate over all descendant nodes that overlap the given two positions (relative to the
+    /// start of this node's content), depth-first.
+    fn descendants_between(&self, from: usize, to: usize) -> Descendants<'_, S> {
+        Descendants::new(
+            self.content().unwrap_or(Fragment::EMPTY_REF),
+            Some(self),
+            from,
+            to,
+        )
+    }
+
+    /// Invoke a callback for all descendant nodes between the given two positions (relative to
+    /// the start of this node's content), with the node, its position, its parent and its index
+    /// in the parent. Doesn't descend into a node when the callback returns `false`.
+    fn nodes_between<F>(&self, from: usize, to: usize, mut f: F)
+    where
+        F: FnMut(&S::Node, usize, Option<&S::Node>, usize) -> bool,
+    {
+        let mut iter = self.descendants_between(from, to);
+        while let Some((node, pos, parent, index)) = iter.next() {
+            if !f(node, pos, parent, index) {
+                iter.skip_children();
+            }
+        }
+    }
+
+    /// Test whether a mark of the given type occurs in this document between the two given
+    /// positions.
+    fn range_has_mark(&self, from: usize, to: usize, mark_type: S::MarkType) -> bool {
+        to > from
+            && self.descendants_between(from, to).any(
+                |(node, ..)| matches!(node.marks(), Some(marks) if marks.find(mark_type).is_some()),
+            )
+    }
+
+    /// Collect every mark on the inline content between the two given positions, together with
+    /// the range it covers (clipped to `from..to`). Adjacent occurrences of the same mark are
+    /// merged into one range. The result is ordered by the start of the ranges.
+    fn marks_in_range(&self, from: usize, to: usize) -> Vec<(S::Mark, Range<usize>)> {
+        let mut result: Vec<(S::Mark, Range<usize>)> = Vec::new();
+        let mut last: HashMap<&S::Mark, usize> = HashMap::new();
+        for (node, pos, ..) in self.descendants_between(from, to) {
+            let marks = match node.marks() {
+                Some(marks) => marks,
+                None => continue,
+            };
+            let range = usize::max(pos, from)..usize::min(pos + node.node_size(), to);
+            for mark in marks {
+                match last.get(mark) {
+                    Some(&index) if result[index].1.end == range.start => {
+                        result[index].1.end = range.end;
+                    }
+                    _ => {
+                        last.insert(mark, result.len());
+                        result.push((mark.clone(), range.clone()));
+                    }
+                }
+            }
+        }
+        result
+    }
+
     /// Concatenates all the text nodes found in this fragment and its children.
     fn text_content(&self) -> String {
         if let Some(node) = self.text_node() {
-            node.text.content.clone()
+            node.text.as_str().to_string()
         } else {
             let mut buf = String::new();
             if let Some(c) = self.content() {
@@ -243,6 +355,68 @@ pub trait Node<S: Schema<Node = Self> + 'static>:
         self.content().is_none()
     }
 
+    /// Compare the markup (type, attributes, and marks) of this node to those of another.
+    /// Returns `true` if both have the same markup.
+    fn same_markup(&self, other: &Self) -> bool {
+        if let Some(text_node) = self.text_node() {
+            text_node.same_markup(other).is_some()
+        } else {
+            self.r#type() == other.r#type()
+                && self.copy(|_| Fragment::new()) == other.copy(|_| Fragment::new())
+        }
+    }
+
+    /// Check whether the attributes of this node conform to the spec of its type.
+    fn validate_attrs(&self) -> Result<(), AttrError> {
+        Ok(())
+    }
+
+    /// Check whether this node and its descendants conform to the schema: the content of
+    /// every node must be valid for its type, marks must be allowed in their parent, attributes
+    /// must be valid and text nodes must be non-empty and joined with equally marked neighbours.
+    fn check(&self) -> Result<(), CheckError<S>> {
+        self.validate_attrs()
+            .map_err(|e| CheckError::new(CheckErrorKind::Attrs(e)))?;
+        if let Some(TextNode { text, .. }) = self.text_node() {
+            if text.len_utf16() == 0 {
+                return Err(CheckError::new(CheckErrorKind::EmptyText));
+            }
+        }
+
+        let content = match self.content() {
+            Some(content) => content,
+            None => return Ok(()),
+        };
+        let node_type = self.r#type();
+        let mut prev: Option<&S::Node> = None;
+        for (i, child) in content.children().iter().enumerate() {
+            for mark in child.marks().into_iter().flatten() {
+                if !node_type.allows_mark_type(mark.r#type()) {
+                    let kind = CheckErrorKind::MarkNotAllowed(node_type, mark.r#type());
+                    return Err(CheckError::at(i, kind));
+                }
+                mark.validate_attrs()
+                    .map_err(|e| CheckError::at(i, CheckErrorKind::Attrs(e)))?;
+            }
+            if let Some(text_node) = prev.and_then(Node::text_node) {
+                if text_node.same_markup(child).is_some() {
+                    return Err(CheckError::at(i, CheckErrorKind::UnjoinedText));
+                }
+            }
+            prev = Some(child);
+        }
+        if !node_type.valid_content(content) {
+            return Err(CheckError::new(CheckErrorKind::InvalidContent(node_type)));
+        }
+        for (i, child) in content.children().iter().enumerate() {
+            child.check().map_err(|mut e| {
+                e.path.insert(0, i);
+                e
+            })?;
+        }
+        Ok(())
+    }
+
     /// Get the content match in this node at the given index.
     fn content_match_at(&self, index: usize) -> Result<S::ContentMatch, ContentMatchError> {
         self.r#type()
@@ -275,12 +449,12 @@ pub trait Node<S: Schema<Node = Self> + 'static>:
         });
 
         if matches!(two, Some(m) if m.valid_end()) {
+            let node_type = self.r#type();
             for i in start..end {
-                if replacement
-                    .child(i)
-                    .marks()
-                    .filter(|m| !self.r#type().allow_marks(m))
-                    .is_some()
+                let marks = replacement.child(i).marks().into_iter().flatten();
+                if marks
+                    .map(Mark::r#type)
+                    .any(|t| !node_type.allows_mark_type(t))
                 {
                     return Ok(false);
                 }
@@ -291,60 +465,3 @@ pub trait Node<S: Schema<Node = Self> + 'static>:
         }
     }
 }
-
-/// A string that stores its length in utf-16
-#[derive(Default, Debug, Clone, PartialEq, Eq, Deserialize)]
-#[serde(from = "String")]
-pub struct Text {
-    len_utf16: usize,
-    content: String,
-}
-
-impl Text {
-    /// Return the contained string
-    pub fn as_str(&self) -> &str {
-        &self.content
-    }
-
-    /// The length of this string if it were encoded in utf-16
-    pub fn len_utf16(&self) -> usize {
-        self.len_utf16
-    }
-
-    /// Join two texts together
-    pub fn join(&self, other: &Self) -> Self {
-        let left = &self.content;
-        let right = &other.content;
-        let mut content = String::with_capacity(left.len() + right.len());
-        content.push_str(left);
-        content.push_str(right);
-        let len_utf16 = self.len_utf16 + other.len_utf16;
-        Text { len_utf16, content }
-    }
-
-    #[cfg(feature = "cmark")]
-    pub(crate) fn remove_last_newline(&mut self) {
-        if let Some('\n') = self.content.chars().next_back() {
-            self.content.pop();
-            self.len_utf16 -= 1;
-        }
-    }
-}
-
-impl From<String> for Text {
-    fn from(src: String) -> Text {
-        Text {
-            len_utf16: src.encode_utf16().count(),
-            content: src,
-        }
-    }
-}
-
-impl Serialize for Text {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        self.content.serialize(serializer)
-    }
-}
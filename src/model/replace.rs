@@ -5,12 +5,16 @@ use derivative::Derivative;
 use displaydoc::Display;
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
+use std::fmt;
 use thiserror::Error;
 
 /// A slice of a fragment
+///
+/// The `Display` implementation prints the content and the open depths, e.g. `<paragraph("a")>(1,1)`.
 #[derive(Derivative, Deserialize, Serialize)]
 #[derivative(
     Debug(bound = ""),
+    Clone(bound = ""),
     PartialEq(bound = ""),
     Eq(bound = ""),
     Default(bound = "")
@@ -45,6 +49,35 @@ impl<S: Schema> Slice<S> {
         }
     }
 
+    /// The size this slice would add when inserted into a document.
+    pub fn size(&self) -> usize {
+        self.content.size() - self.open_start - self.open_end
+    }
+
+    /// The slice between two resolved positions in the same document
+    pub(crate) fn between(
+        rp_from: &ResolvedPos<S>,
+        rp_to: &ResolvedPos<S>,
+        include_parents: bool,
+    ) -> Slice<S> {
+        if rp_from.pos == rp_to.pos {
+            return Slice::default();
+        }
+        let depth = if include_parents {
+            0
+        } else {
+            rp_from.shared_depth(rp_to.pos)
+        };
+
+        let (start, node) = (rp_from.start(depth), rp_from.node(depth));
+        let content = if let Some(c) = node.content() {
+            c.cut(rp_from.pos - start..rp_to.pos - start)
+        } else {
+            Fragment::new()
+        };
+        Slice::new(content, rp_from.depth - depth, rp_to.depth - depth)
+    }
+
     pub(crate) fn insert_at(
         &self,
         pos: usize,
@@ -55,6 +88,12 @@ impl<S: Schema> Slice<S> {
     }
 }
 
+impl<S: Schema> fmt::Display for Slice<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({},{})", self.content, self.open_start, self.open_end)
+    }
+}
+
 /// Error on insertion
 #[derive(Debug, Display, Error)]
 pub enum InsertError {
@@ -348,9 +387,9 @@ fn prepare_slice_for_replace<'a, S: Schema>(
 #[cfg(test)]
 mod tests {
     use super::ReplaceError;
-    use crate::markdown::helper::{blockquote, doc, h1, li, p, ul};
+    use crate::markdown::helper::{a, blockquote, doc, em, h1, li, p, ul};
     use crate::markdown::{MarkdownNode, MarkdownNodeType, MD};
-    use crate::model::{Fragment, Node, Slice, SliceError};
+    use crate::model::{Fragment, Node, Slice, SliceError, TaggedNode};
     use displaydoc::Display;
     use std::fmt::Debug;
     use std::ops::{Range, RangeBounds};
@@ -615,4 +654,168 @@ mod tests {
 
         bad((t, 1..6), Some((i, 3..4)), e);
     }
+
+    // The `test-slice` suite of prosemirror-model
+
+    fn cut(d: TaggedNode<MD>, expect: Fragment<MD>, open_start: usize, open_end: usize) {
+        let from = d.tags().get("a").copied().unwrap_or(0);
+        let to = d.tags().get("b").copied();
+        let slice = match to {
+            Some(to) => d.slice(from..to, false),
+            None => d.slice(from.., false),
+        };
+        assert_eq!(slice, Ok(Slice::new(expect, open_start, open_end)));
+    }
+
+    fn content(node: TaggedNode<MD>) -> Fragment<MD> {
+        node.content().unwrap().clone()
+    }
+
+    #[test]
+    fn can_cut_half_a_paragraph() {
+        cut(doc!(p!("hello<b> world")), content(doc!(p!("hello"))), 0, 1);
+    }
+
+    #[test]
+    fn can_cut_to_the_end_of_a_paragraph() {
+        cut(doc!(p!("hello<b>")), content(doc!(p!("hello"))), 0, 1);
+    }
+
+    #[test]
+    fn leaves_off_extra_content() {
+        let d = doc!(p!("hello<b> world"), p!("rest"));
+        cut(d, content(doc!(p!("hello"))), 0, 1);
+    }
+
+    #[test]
+    fn preserves_styles() {
+        let d = doc!(p!("hello ", em!("WOR<b>LD")));
+        cut(d, content(doc!(p!("hello ", em!("WOR")))), 0, 1);
+    }
+
+    #[test]
+    fn can_cut_multiple_blocks() {
+        cut(
+            doc!(p!("a"), p!("b<b>")),
+            content(doc!(p!("a"), p!("b"))),
+            0,
+            1,
+        );
+    }
+
+    #[test]
+    fn can_cut_to_a_top_level_position() {
+        cut(doc!(p!("a"), "<b>", p!("b")), content(doc!(p!("a"))), 0, 0);
+    }
+
+    #[test]
+    fn can_cut_to_a_deep_position() {
+        let d = doc!(blockquote!(ul!(li!(p!("a")), li!(p!("b<b>")))));
+        let e = doc!(blockquote!(ul!(li!(p!("a")), li!(p!("b")))));
+        cut(d, content(e), 0, 4);
+    }
+
+    #[test]
+    fn can_cut_everything_after_a_position() {
+        cut(
+            doc!(p!("hello<a> world")),
+            content(doc!(p!(" world"))),
+            1,
+            0,
+        );
+    }
+
+    #[test]
+    fn can_cut_from_the_start_of_a_textblock() {
+        cut(doc!(p!("<a>hello")), content(doc!(p!("hello"))), 1, 0);
+    }
+
+    #[test]
+    fn leaves_off_extra_content_before() {
+        let d = doc!(p!("foo"), p!("bar<a>baz"));
+        cut(d, content(doc!(p!("baz"))), 1, 0);
+    }
+
+    #[test]
+    fn preserves_styles_after_cut() {
+        let d = doc!(p!(
+            "a sentence with an ",
+            em!("emphasized ", a!(href = "foo"; "li<a>nk")),
+            " in it"
+        ));
+        let e = doc!(p!(em!(a!(href = "foo"; "nk")), " in it"));
+        cut(d, content(e), 1, 0);
+    }
+
+    #[test]
+    fn preserves_styles_started_after_cut() {
+        let d = doc!(p!(
+            "a ",
+            em!("sentence"),
+            " wit<a>h ",
+            em!("text"),
+            " in it"
+        ));
+        cut(d, content(doc!(p!("h ", em!("text"), " in it"))), 1, 0);
+    }
+
+    #[test]
+    fn can_cut_from_a_top_level_position() {
+        cut(doc!(p!("a"), "<a>", p!("b")), content(doc!(p!("b"))), 0, 0);
+    }
+
+    #[test]
+    fn can_cut_from_a_deep_position() {
+        let d = doc!(blockquote!(ul!(li!(p!("a")), li!(p!("<a>b")))));
+        let e = doc!(blockquote!(ul!(li!(p!("b")))));
+        cut(d, content(e), 4, 0);
+    }
+
+    #[test]
+    fn can_cut_part_of_a_text_node() {
+        cut(doc!(p!("hell<a>o wo<b>rld")), content(p!("o wo")), 0, 0);
+    }
+
+    #[test]
+    fn can_cut_across_paragraphs() {
+        let d = doc!(p!("on<a>e"), p!("t<b>wo"));
+        cut(d, content(doc!(p!("e"), p!("t"))), 1, 1);
+    }
+
+    #[test]
+    fn can_cut_part_of_marked_text() {
+        let d = doc!(p!("here's noth<a>ing and ", em!("here's e<b>m")));
+        cut(d, content(p!("ing and ", em!("here's e"))), 0, 0);
+    }
+
+    #[test]
+    fn can_cut_across_different_depths() {
+        let d = doc!(
+            ul!(li!(p!("hello")), li!(p!("wo<a>rld")), li!(p!("x"))),
+            p!(em!("bo<b>o"))
+        );
+        let e = doc!(ul!(li!(p!("rld")), li!(p!("x"))), p!(em!("bo")));
+        cut(d, content(e), 3, 1);
+    }
+
+    #[test]
+    fn can_cut_between_deeper_nodes() {
+        let d = doc!(blockquote!(
+            p!("foo<a>bar"),
+            ul!(li!(p!("a")), li!(p!("b"), "<b>", p!("c"))),
+            p!("d")
+        ));
+        let e = blockquote!(p!("bar"), ul!(li!(p!("a")), li!(p!("b"))));
+        cut(d, content(e), 1, 2);
+    }
+
+    #[test]
+    fn can_include_parents() {
+        let d = doc!(blockquote!(p!("fo<a>o"), p!("bar<b>")));
+        let slice = d.slice(d.tag("a")..d.tag("b"), true).unwrap();
+        assert_eq!(
+            slice.to_string(),
+            r#"<blockquote(paragraph("o"), paragraph("bar"))>(2,2)"#
+        );
+    }
 }
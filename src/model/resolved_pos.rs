@@ -1,4 +1,4 @@
-use super::{fragment::IndexError, Fragment, Node, Schema};
+use super::{fragment::IndexError, Fragment, Mark, MarkSet, MarkType, Node, Schema, TextNode};
 use derivative::Derivative;
 use displaydoc::Display;
 use std::borrow::Cow;
@@ -147,6 +147,57 @@ impl<'a, S: Schema> ResolvedPos<'a, S> {
         }
     }
 
+    /// The (absolute) position of the child at the given index in the ancestor at the given level.
+    pub fn pos_at_index(&self, index: usize, depth: usize) -> usize {
+        let node = self.node(depth);
+        let start = self.start(depth);
+        (0..index).fold(start, |pos, i| pos + node.child(i).unwrap().node_size())
+    }
+
+    /// The marks at this position, factoring in the surrounding marks' [`inclusive`] property.
+    /// If the position is at the start of a non-empty node, the marks of the node after it (if
+    /// any) are returned.
+    ///
+    /// [`inclusive`]: crate::model::MarkType::inclusive
+    pub fn marks(&self) -> MarkSet<S> {
+        let parent = self.parent();
+        let index = self.index(self.depth);
+
+        if parent.content().map_or(0, |c| c.size()) == 0 {
+            return MarkSet::default();
+        }
+        if self.text_offset() > 0 {
+            return parent
+                .child(index)
+                .and_then(Node::marks)
+                .cloned()
+                .unwrap_or_default();
+        }
+
+        let before = index.checked_sub(1).and_then(|i| parent.maybe_child(i));
+        let after = parent.maybe_child(index);
+        let (main, other) = match before {
+            Some(before) => (before, after),
+            None => match after {
+                Some(after) => (after, None),
+                None => return MarkSet::default(),
+            },
+        };
+
+        let main_marks = main.marks().cloned().unwrap_or_default();
+        let mut marks = Cow::Borrowed(&main_marks);
+        for mark in &main_marks {
+            let kept = mark.r#type().inclusive()
+                || other
+                    .and_then(Node::marks)
+                    .is_some_and(|m| m.contains(mark));
+            if !kept {
+                marks = mark.remove_from_set(marks);
+            }
+        }
+        marks.into_owned()
+    }
+
     /// When this position points into a text node, this returns the
     /// distance between the position and the start of the text node.
     /// Will be zero for positions that point between nodes.
@@ -154,6 +205,38 @@ impl<'a, S: Schema> ResolvedPos<'a, S> {
         self.pos - self.path.last().unwrap().before
     }
 
+    /// The text node that this position points into or that starts directly after it, along with
+    /// the (absolute) position at the start of that node. At the end of a text node that is not
+    /// followed by another one, this is the text node before the position.
+    pub fn text_node(&self) -> Option<(&'a TextNode<S>, usize)> {
+        let parent = self.node(self.depth);
+        let index = self.index(self.depth);
+        if let Some(text) = parent.maybe_child(index).and_then(Node::text_node) {
+            return Some((text, self.path.last().unwrap().before));
+        }
+        let before = index.checked_sub(1).and_then(|i| parent.maybe_child(i))?;
+        let text = before.text_node()?;
+        Some((text, self.pos - before.node_size()))
+    }
+
+    /// The offset of this position in bytes into the utf-8 content of its [text node](Self::text_node).
+    /// Positions in the middle of a surrogate pair have no such offset.
+    ///
+    /// To convert back, add [`Text::byte_to_utf16`](crate::model::Text::byte_to_utf16) to the start of the text node.
+    pub fn text_byte_offset(&self) -> Option<usize> {
+        let (node, start) = self.text_node()?;
+        node.text.utf16_to_byte(self.pos - start)
+    }
+
+    /// The offset of this position in unicode scalar values (`char`s) into its
+    /// [text node](Self::text_node).
+    ///
+    /// To convert back, add [`Text::char_to_utf16`](crate::model::Text::char_to_utf16) to the start of the text node.
+    pub fn text_char_offset(&self) -> Option<usize> {
+        let (node, start) = self.text_node()?;
+        node.text.utf16_to_char(self.pos - start)
+    }
+
     /// Get the node directly before the position, if any. If the position points into a text node,
     /// only the part of that node before the position is returned.
     pub fn node_before(&self) -> Option<Cow<S::Node>> {
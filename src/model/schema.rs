@@ -1,20 +1,24 @@
-use super::{ContentMatch, Fragment, Mark, MarkSet, Node, NodeType, Text};
+use super::{AttrSpec, ContentMatch, Fragment, Mark, MarkSet, Node, NodeType, Text};
+use crate::de;
 use derivative::Derivative;
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 
 /// This type represents a schema.
-pub trait Schema: Sized + 'static {
+///
+/// All of the types are required to be `Send + Sync`, so that documents and steps can be shared
+/// between threads.
+pub trait Schema: Sized + Send + Sync + 'static {
     /// This type represents any of the marks that are valid in the schema.
-    type Mark: Mark<Self>;
+    type Mark: Mark<Self> + Send + Sync;
     /// This type represents any of the mark types that are valid in the schema.
-    type MarkType: MarkType;
+    type MarkType: MarkType + Send + Sync;
     /// This type represents any of the nodes that are valid in the schema.
-    type Node: Node<Self>;
+    type Node: Node<Self> + Send + Sync;
     /// This type represents any of the node types that are valid in the schema.
-    type NodeType: NodeType<Self>;
+    type NodeType: NodeType<Self> + Send + Sync;
     /// This type represents the `ContentMatch` impl
-    type ContentMatch: ContentMatch<Self>;
+    type ContentMatch: ContentMatch<Self> + Send + Sync;
 }
 
 /// A simple block node
@@ -29,7 +33,7 @@ pub trait Schema: Sized + 'static {
 #[serde(bound = "")]
 pub struct Block<S: Schema> {
     /// The content.
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Fragment::is_empty")]
     #[derivative(Debug(bound = ""))]
     pub content: Fragment<S>,
 }
@@ -55,13 +59,14 @@ impl<S: Schema> Block<S> {
     PartialEq(bound = "A: PartialEq"),
     Eq(bound = "A: Eq")
 )]
-#[serde(bound = "A: for<'d> Deserialize<'d> + Serialize")]
+#[serde(bound = "A: for<'d> Deserialize<'d> + Serialize + AttrSpec + Default")]
 pub struct AttrNode<S: Schema, A> {
     /// Attributes
+    #[serde(default, deserialize_with = "de::deserialize_attrs")]
     pub attrs: A,
 
     /// The content.
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Fragment::is_empty")]
     #[derivative(Debug(bound = ""))]
     pub content: Fragment<S>,
 }
@@ -92,7 +97,7 @@ impl<S: Schema, A: Clone> AttrNode<S, A> {
 pub struct TextNode<S: Schema> {
     // todo: replace with typemap
     /// Marks on this node
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "MarkSet::is_empty")]
     pub marks: MarkSet<S>,
     /// The actual text
     pub text: Text,
@@ -115,8 +120,10 @@ impl<S: Schema> TextNode<S> {
 
 /// A leaf node (just attributes)
 #[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(bound(deserialize = "A: for<'d> Deserialize<'d> + AttrSpec"))]
 pub struct Leaf<A> {
     /// Attributes
+    #[serde(deserialize_with = "de::deserialize_attrs")]
     pub attrs: A,
 }
 
@@ -124,4 +131,12 @@ pub struct Leaf<A> {
 /// things like emphasis or being part of a link) are
 /// [tagged](#model.Mark.type) with type objects, which are
 /// instantiated once per `Schema`.
-pub trait MarkType: Copy + Clone + Debug + PartialEq + Eq + PartialOrd + Ord {}
+pub trait MarkType: Copy + Clone + Debug + PartialEq + Eq + PartialOrd + Ord {
+    /// The name of this mark type in the schema, e.g. `em`.
+    fn name(self) -> &'static str;
+
+    /// Whether marks of this type should be active when the cursor is positioned at their end.
+    fn inclusive(self) -> bool {
+        true
+    }
+}
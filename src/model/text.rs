@@ -0,0 +1,217 @@
+use serde::{Deserialize, Serialize, Serializer};
+use std::sync::Arc;
+use unicode_segmentation::GraphemeCursor;
+
+/// A string that stores its length in utf-16
+///
+/// ProseMirror positions count utf-16 code units, while Rust strings are indexed by bytes. The
+/// conversion methods on this type translate between offsets into the text in these units, and
+/// unicode scalar values (`char`s). They return `None` for offsets that are out of bounds or not
+/// on a character boundary.
+///
+/// The content is reference counted, so cloning a text is cheap.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(from = "String")]
+pub struct Text {
+    len_utf16: usize,
+    content: Arc<str>,
+}
+
+impl Text {
+    /// Return the contained string
+    pub fn as_str(&self) -> &str {
+        &self.content
+    }
+
+    /// The length of this string if it were encoded in utf-16
+    pub fn len_utf16(&self) -> usize {
+        self.len_utf16
+    }
+
+    /// Convert an offset in utf-16 code units to a byte offset
+    pub fn utf16_to_byte(&self, offset: usize) -> Option<usize> {
+        self.find(offset, |_, utf16, _| utf16)
+            .map(|(byte, _, _)| byte)
+    }
+
+    /// Convert a byte offset to an offset in utf-16 code units
+    pub fn byte_to_utf16(&self, byte: usize) -> Option<usize> {
+        self.find(byte, |byte, _, _| byte)
+            .map(|(_, utf16, _)| utf16)
+    }
+
+    /// Convert an offset in utf-16 code units to an offset in `char`s
+    pub fn utf16_to_char(&self, offset: usize) -> Option<usize> {
+        self.find(offset, |_, utf16, _| utf16)
+            .map(|(_, _, chars)| chars)
+    }
+
+    /// Convert an offset in `char`s to an offset in utf-16 code units
+    pub fn char_to_utf16(&self, index: usize) -> Option<usize> {
+        self.find(index, |_, _, chars| chars)
+            .map(|(_, utf16, _)| utf16)
+    }
+
+    /// Find the character boundary where `key` reaches `target`, returning its
+    /// `(byte, utf16, char)` offsets
+    fn find<F>(&self, target: usize, key: F) -> Option<(usize, usize, usize)>
+    where
+        F: Fn(usize, usize, usize) -> usize,
+    {
+        let (mut utf16, mut chars) = (0, 0);
+        for (byte, c) in self.content.char_indices() {
+            match key(byte, utf16, chars) {
+                k if k == target => return Some((byte, utf16, chars)),
+                k if k > target => return None,
+                _ => {}
+            }
+            utf16 += c.len_utf16();
+            chars += 1;
+        }
+        match key(self.content.len(), utf16, chars) {
+            k if k == target => Some((self.content.len(), utf16, chars)),
+            _ => None,
+        }
+    }
+
+    /// Whether the utf-16 offset is on the boundary between two grapheme clusters
+    /// (user-perceived characters), or at the start or end of the text.
+    pub fn is_grapheme_boundary(&self, offset: usize) -> bool {
+        let byte = match self.utf16_to_byte(offset) {
+            Some(byte) => byte,
+            None => return false,
+        };
+        let mut cursor = GraphemeCursor::new(byte, self.content.len(), true);
+        cursor.is_boundary(&self.content, 0).unwrap_or(false)
+    }
+
+    /// The utf-16 offset of the next grapheme boundary after the given one, if any
+    pub fn next_grapheme_boundary(&self, offset: usize) -> Option<usize> {
+        let byte = self.utf16_to_byte(offset)?;
+        let mut cursor = GraphemeCursor::new(byte, self.content.len(), true);
+        let next = cursor.next_boundary(&self.content, 0).ok()??;
+        self.byte_to_utf16(next)
+    }
+
+    /// The utf-16 offset of the previous grapheme boundary before the given one, if any
+    pub fn prev_grapheme_boundary(&self, offset: usize) -> Option<usize> {
+        let byte = self.utf16_to_byte(offset)?;
+        let mut cursor = GraphemeCursor::new(byte, self.content.len(), true);
+        let prev = cursor.prev_boundary(&self.content, 0).ok()??;
+        self.byte_to_utf16(prev)
+    }
+
+    /// Join two texts together
+    pub fn join(&self, other: &Self) -> Self {
+        let left = &self.content;
+        let right = &other.content;
+        let mut content = String::with_capacity(left.len() + right.len());
+        content.push_str(left);
+        content.push_str(right);
+        let len_utf16 = self.len_utf16 + other.len_utf16;
+        Text {
+            len_utf16,
+            content: content.into(),
+        }
+    }
+
+    #[cfg(feature = "cmark")]
+    pub(crate) fn remove_last_newline(&mut self) {
+        if let Some(rest) = self.content.strip_suffix('\n') {
+            self.content = rest.into();
+            self.len_utf16 -= 1;
+        }
+    }
+}
+
+impl From<String> for Text {
+    fn from(src: String) -> Text {
+        Text {
+            len_utf16: src.encode_utf16().count(),
+            content: src.into(),
+        }
+    }
+}
+
+impl Serialize for Text {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.content.serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Text;
+    use crate::markdown::helper::{doc, em, img, node, p};
+    use crate::model::Node;
+
+    #[test]
+    fn test_offsets() {
+        // 'ä' is two bytes and one utf-16 unit, the emoji four bytes and two units
+        let text = Text::from("aä\u{1F60A}b".to_owned());
+        assert_eq!(text.len_utf16(), 5);
+        let units = [(0, 0, 0), (1, 1, 1), (2, 3, 2), (4, 7, 3), (5, 8, 4)];
+        for &(utf16, byte, chars) in &units {
+            assert_eq!(text.utf16_to_byte(utf16), Some(byte));
+            assert_eq!(text.byte_to_utf16(byte), Some(utf16));
+            assert_eq!(text.utf16_to_char(utf16), Some(chars));
+            assert_eq!(text.char_to_utf16(chars), Some(utf16));
+        }
+        // Inside a character or out of bounds
+        assert_eq!(text.utf16_to_byte(3), None);
+        assert_eq!(text.byte_to_utf16(2), None);
+        assert_eq!(text.utf16_to_char(6), None);
+        assert_eq!(text.char_to_utf16(5), None);
+    }
+
+    #[test]
+    fn test_grapheme_boundaries() {
+        // 'e' with a combining acute accent, and a family emoji joined with zero width joiners
+        let text = Text::from("e\u{301}x\u{1F468}\u{200D}\u{1F469}".to_owned());
+        assert!(text.is_grapheme_boundary(0));
+        assert!(!text.is_grapheme_boundary(1));
+        assert!(text.is_grapheme_boundary(2));
+        assert!(!text.is_grapheme_boundary(5));
+        assert!(text.is_grapheme_boundary(8));
+        assert_eq!(text.next_grapheme_boundary(0), Some(2));
+        assert_eq!(text.next_grapheme_boundary(3), Some(8));
+        assert_eq!(text.next_grapheme_boundary(8), None);
+        assert_eq!(text.prev_grapheme_boundary(8), Some(3));
+        assert_eq!(text.prev_grapheme_boundary(2), Some(0));
+        assert_eq!(text.prev_grapheme_boundary(0), None);
+    }
+
+    #[test]
+    fn test_resolved_text_offsets() {
+        let test_doc = doc(p((node("ä\u{1F60A}"), em("ö"))));
+        let offsets = |pos| {
+            let rp = test_doc.resolve(pos).unwrap();
+            let start = rp.text_node().map(|(_, start)| start);
+            (start, rp.text_byte_offset(), rp.text_char_offset())
+        };
+        assert_eq!(offsets(0), (None, None, None));
+        assert_eq!(offsets(1), (Some(1), Some(0), Some(0)));
+        assert_eq!(offsets(2), (Some(1), Some(2), Some(1)));
+        assert_eq!(offsets(3), (Some(1), None, None));
+        assert_eq!(offsets(4), (Some(4), Some(0), Some(0)));
+        // The end of the last text node
+        assert_eq!(offsets(5), (Some(4), Some(2), Some(1)));
+        assert_eq!(offsets(6), (None, None, None));
+
+        // The end of a text node that is followed by an image
+        let with_image = doc(p((node("ab"), img!(src = "x.png").node)));
+        let rp = with_image.resolve(3).unwrap();
+        assert_eq!(rp.text_node().map(|(_, start)| start), Some(1));
+        assert_eq!(
+            (rp.text_byte_offset(), rp.text_char_offset()),
+            (Some(2), Some(2))
+        );
+
+        // Convert back from a byte offset in a text node to a position
+        let (text, start) = test_doc.resolve(2).unwrap().text_node().unwrap();
+        assert_eq!(text.text.byte_to_utf16(6).map(|o| start + o), Some(4));
+    }
+}
@@ -0,0 +1,13 @@
+use serde::Serializer;
+
+/// Serialize an empty string as `null`, like the attributes that default to `null` in JS.
+pub fn serialize_empty_as_null<S>(value: &str, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    if value.is_empty() {
+        serializer.serialize_none()
+    } else {
+        serializer.serialize_str(value)
+    }
+}
@@ -0,0 +1,104 @@
+/// A map describing the deletions and insertions made by a step, which
+/// can be used to find the correspondence between positions in the
+/// pre-step version of a document and the same position in the
+/// post-step version.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct StepMap {
+    ranges: Vec<usize>,
+    inverted: bool,
+}
+
+impl StepMap {
+    /// A step map that contains no changed ranges.
+    pub const EMPTY: StepMap = StepMap {
+        ranges: Vec::new(),
+        inverted: false,
+    };
+
+    /// Create a position map. The modifications to the document are
+    /// represented as an array of numbers, in which each group of three
+    /// represents a modified chunk as `[start, oldSize, newSize]`.
+    pub fn new(ranges: Vec<usize>) -> Self {
+        debug_assert_eq!(ranges.len() % 3, 0);
+        StepMap {
+            ranges,
+            inverted: false,
+        }
+    }
+
+    fn indices(&self) -> (usize, usize) {
+        if self.inverted {
+            (2, 1)
+        } else {
+            (1, 2)
+        }
+    }
+
+    /// Map a position through this map. `assoc` determines on which side
+    /// the position is associated when content is inserted at it: a
+    /// negative value means the position stays before the inserted content.
+    pub fn map(&self, pos: usize, assoc: isize) -> usize {
+        let (old_index, new_index) = self.indices();
+        let mut diff: isize = 0;
+        for range in self.ranges.chunks(3) {
+            let start = if self.inverted {
+                (range[0] as isize - diff) as usize
+            } else {
+                range[0]
+            };
+            if start > pos {
+                break;
+            }
+            let (old_size, new_size) = (range[old_index], range[new_index]);
+            let end = start + old_size;
+            if pos <= end {
+                let side = if old_size == 0 {
+                    assoc
+                } else if pos == start {
+                    -1
+                } else if pos == end {
+                    1
+                } else {
+                    assoc
+                };
+                let offset = if side < 0 { 0 } else { new_size };
+                return (start as isize + diff) as usize + offset;
+            }
+            diff += new_size as isize - old_size as isize;
+        }
+        (pos as isize + diff) as usize
+    }
+
+    /// Calls the given function on each of the changed ranges included in
+    /// this map, with the arguments `old_start, old_end, new_start, new_end`.
+    pub fn for_each<F: FnMut(usize, usize, usize, usize)>(&self, mut f: F) {
+        let (old_index, new_index) = self.indices();
+        let mut diff: isize = 0;
+        for range in self.ranges.chunks(3) {
+            let start = range[0] as isize;
+            let (old_start, new_start) = if self.inverted {
+                (start - diff, start)
+            } else {
+                (start, start + diff)
+            };
+            let (old_size, new_size) = (range[old_index], range[new_index]);
+            let (old_start, new_start) = (old_start as usize, new_start as usize);
+            f(
+                old_start,
+                old_start + old_size,
+                new_start,
+                new_start + new_size,
+            );
+            diff += new_size as isize - old_size as isize;
+        }
+    }
+
+    /// Create an inverted version of this map. The result can be used to
+    /// map positions in the post-step document to the pre-step document.
+    pub fn invert(&self) -> StepMap {
+        StepMap {
+            ranges: self.ranges.clone(),
+            inverted: !self.inverted,
+        }
+    }
+}
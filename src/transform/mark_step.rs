@@ -1,5 +1,5 @@
-use super::{util::Span, StepKind};
-use crate::model::{Fragment, Mark, MarkSet, Node, NodeType, Schema, Slice};
+use super::{util::Span, StepKind, StepMap};
+use crate::model::{replace, Fragment, Mark, MarkSet, Node, NodeType, Schema, Slice};
 use derivative::Derivative;
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
@@ -17,7 +17,18 @@ where
         }
         mapped.push(child)
     }
-    Fragment::from(mapped)
+    Fragment::from_array(mapped)
+}
+
+fn merge_spans<M: PartialEq>(a: Span, b: Span, mark_a: &M, mark_b: &M) -> Option<Span> {
+    if mark_a == mark_b && a.from <= b.to && a.to >= b.from {
+        Some(Span {
+            from: a.from.min(b.from),
+            to: a.to.max(b.to),
+        })
+    } else {
+        None
+    }
 }
 
 fn map_fragment<S: Schema, F>(fragment: &Fragment<S>, f: &F) -> Fragment<S>
@@ -33,12 +44,17 @@ where
         }
         mapped.push(child)
     }
-    Fragment::from(mapped)
+    Fragment::from_array(mapped)
 }
 
 /// Adding a mark on some part of the document
 #[derive(Derivative, Deserialize, Serialize)]
-#[derivative(Debug(bound = ""), PartialEq(bound = ""), Eq(bound = ""))]
+#[derivative(
+    Debug(bound = ""),
+    Clone(bound = ""),
+    PartialEq(bound = ""),
+    Eq(bound = "")
+)]
 #[serde(bound = "", rename_all = "camelCase")]
 pub struct AddMarkStep<S: Schema> {
     /// The affected part of the document
@@ -50,7 +66,12 @@ pub struct AddMarkStep<S: Schema> {
 
 /// Removing a mark on some part of the document
 #[derive(Derivative, Deserialize, Serialize)]
-#[derivative(Debug(bound = ""), PartialEq(bound = ""), Eq(bound = ""))]
+#[derivative(
+    Debug(bound = ""),
+    Clone(bound = ""),
+    PartialEq(bound = ""),
+    Eq(bound = "")
+)]
 #[serde(bound = "", rename_all = "camelCase")]
 pub struct RemoveMarkStep<S: Schema> {
     /// The affected part of the document
@@ -60,10 +81,22 @@ pub struct RemoveMarkStep<S: Schema> {
     pub mark: S::Mark,
 }
 
+impl<S: Schema> AddMarkStep<S> {
+    /// Merge with a step for the same mark, to be applied directly after this one, if their
+    /// spans overlap or touch.
+    pub fn merge(&self, other: &Self) -> Option<Self> {
+        merge_spans(self.span, other.span, &self.mark, &other.mark).map(|span| AddMarkStep {
+            span,
+            mark: self.mark.clone(),
+        })
+    }
+}
+
 impl<S: Schema> StepKind<S> for AddMarkStep<S> {
     fn apply(&self, doc: &S::Node) -> super::StepResult<S> {
-        let old_slice = doc.slice(self.span.from..self.span.to, false)?;
         let rp_from = doc.resolve(self.span.from)?;
+        let rp_to = doc.resolve(self.span.to)?;
+        let old_slice = Slice::between(&rp_from, &rp_to, false);
         let parent = rp_from.node(rp_from.shared_depth(self.span.to));
 
         let new_content = map_fragment_parent(
@@ -81,14 +114,31 @@ impl<S: Schema> StepKind<S> for AddMarkStep<S> {
 
         let slice = Slice::new(new_content, old_slice.open_start, old_slice.open_end);
         // TODO: Cow::Owned?
-        let new_node = doc.replace(self.span.from..self.span.to, &slice)?;
+        let new_node = replace(&rp_from, &rp_to, &slice)?;
         Ok(new_node)
     }
+
+    fn get_map(&self) -> StepMap {
+        StepMap::EMPTY
+    }
+}
+
+impl<S: Schema> RemoveMarkStep<S> {
+    /// Merge with a step for the same mark, to be applied directly after this one, if their
+    /// spans overlap or touch.
+    pub fn merge(&self, other: &Self) -> Option<Self> {
+        merge_spans(self.span, other.span, &self.mark, &other.mark).map(|span| RemoveMarkStep {
+            span,
+            mark: self.mark.clone(),
+        })
+    }
 }
 
 impl<S: Schema> StepKind<S> for RemoveMarkStep<S> {
     fn apply(&self, doc: &S::Node) -> super::StepResult<S> {
-        let old_slice = doc.slice(self.span.from..self.span.to, false)?;
+        let rp_from = doc.resolve(self.span.from)?;
+        let rp_to = doc.resolve(self.span.to)?;
+        let old_slice = Slice::between(&rp_from, &rp_to, false);
 
         let new_content = map_fragment(&old_slice.content, &|node| {
             let new_marks: Cow<MarkSet<S>> = node.marks().map(Cow::Borrowed).unwrap_or_default();
@@ -96,7 +146,11 @@ impl<S: Schema> StepKind<S> for RemoveMarkStep<S> {
         });
 
         let slice = Slice::new(new_content, old_slice.open_start, old_slice.open_end);
-        let new_node = doc.replace(self.span.from..self.span.to, &slice)?;
+        let new_node = replace(&rp_from, &rp_to, &slice)?;
         Ok(new_node)
     }
+
+    fn get_map(&self) -> StepMap {
+        StepMap::EMPTY
+    }
 }
@@ -1,11 +1,15 @@
 //! # The document transformations
 //!
+mod map;
 mod mark_step;
+mod recreate;
 mod replace_step;
 mod step;
 mod util;
 
+pub use map::StepMap;
 pub use mark_step::{AddMarkStep, RemoveMarkStep};
+pub use recreate::{recreate_steps, RecreateError};
 pub use replace_step::{ReplaceAroundStep, ReplaceStep};
 pub use step::{StepError, StepKind, StepResult};
 pub use util::Span;
@@ -20,7 +24,12 @@ pub type Steps<S: Schema> = Vec<Step<S>>;
 
 /// Steps that can be applied on a document
 #[derive(Derivative, Deserialize, Serialize)]
-#[derivative(Debug(bound = ""), PartialEq(bound = ""), Eq(bound = ""))]
+#[derivative(
+    Debug(bound = ""),
+    Clone(bound = ""),
+    PartialEq(bound = ""),
+    Eq(bound = "")
+)]
 #[serde(bound = "", tag = "stepType", rename_all = "camelCase")]
 pub enum Step<S: Schema> {
     /// Replace some content
@@ -43,16 +52,47 @@ impl<S: Schema> Step<S> {
             Self::RemoveMark(rm_step) => rm_step.apply(doc),
         }
     }
+
+    /// Get the step map that represents the changes made by this step
+    pub fn get_map(&self) -> StepMap {
+        match self {
+            Self::Replace(r_step) => r_step.get_map(),
+            Self::ReplaceAround(ra_step) => ra_step.get_map(),
+            Self::AddMark(am_step) => am_step.get_map(),
+            Self::RemoveMark(rm_step) => rm_step.get_map(),
+        }
+    }
+
+    /// Try to merge this step with another one, to be applied directly after it. Returns the
+    /// merged step when possible, `None` if the steps can't be merged.
+    pub fn merge(&self, other: &Self) -> Option<Self> {
+        match (self, other) {
+            (Self::Replace(a), Self::Replace(b)) => a.merge(b).map(Self::Replace),
+            (Self::AddMark(a), Self::AddMark(b)) => a.merge(b).map(Self::AddMark),
+            (Self::RemoveMark(a), Self::RemoveMark(b)) => a.merge(b).map(Self::RemoveMark),
+            _ => None,
+        }
+    }
+}
+
+#[allow(dead_code)]
+fn assert_send_sync<S: Schema>() {
+    use crate::util::assert_send_sync;
+    assert_send_sync::<Step<S>>();
+    assert_send_sync::<Steps<S>>();
+    assert_send_sync::<StepMap>();
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{AddMarkStep, ReplaceStep, Span, Step, StepKind};
+    use super::{
+        AddMarkStep, RemoveMarkStep, ReplaceAroundStep, ReplaceStep, Span, Step, StepKind,
+    };
     use crate::markdown::{
-        helper::{doc, node, p, strong},
-        MarkdownMark, MarkdownNode, MD,
+        helper::{a, blockquote, br, code, doc, em, h1, h2, hr, li, node, ol, p, pre, strong},
+        LinkAttrs, MarkdownMark, MarkdownNode, MD,
     };
-    use crate::model::{Fragment, Node, Slice};
+    use crate::model::{Fragment, Node, Slice, TaggedNode};
 
     #[test]
     fn test_apply() {
@@ -65,6 +105,89 @@ mod tests {
         assert_eq!(d2, doc(p(vec![strong("Hello Wo"), node("rld!")])));
     }
 
+    #[test]
+    fn test_share_between_threads() {
+        let d1 = doc(p("Hello World!"));
+        let step = Step::<MD>::AddMark(AddMarkStep {
+            span: Span { from: 1, to: 6 },
+            mark: MarkdownMark::Strong,
+        });
+
+        let snapshot = d1.clone();
+        let reader = std::thread::spawn(move || snapshot.text_content());
+        let writer = std::thread::spawn(move || step.apply(&d1).unwrap());
+
+        assert_eq!(reader.join().unwrap(), "Hello World!");
+        assert_eq!(
+            writer.join().unwrap(),
+            doc(p(vec![strong("Hello"), node(" World!")]))
+        );
+    }
+
+    #[test]
+    fn test_structural_sharing() {
+        let d1 = doc(vec![p("Hello"), blockquote(vec![p("World"), p("!")])]);
+        let step = Step::<MD>::Replace(ReplaceStep {
+            span: Span { from: 10, to: 10 },
+            slice: Slice::new(Fragment::from((MarkdownNode::text("x"),)), 0, 0),
+            structure: false,
+        });
+        let d2 = step.apply(&d1).unwrap();
+        assert_eq!(
+            d2,
+            doc(vec![p("Hello"), blockquote(vec![p("Wxorld"), p("!")])])
+        );
+
+        let content = |node: &MarkdownNode, path: &[usize]| {
+            let node = path
+                .iter()
+                .fold(node.clone(), |n, &i| n.child(i).unwrap().clone());
+            node.content().unwrap().clone()
+        };
+        // The nodes on the path to the change are new, the others are shared
+        assert!(!content(&d1, &[]).ptr_eq(&content(&d2, &[])));
+        assert!(!content(&d1, &[1]).ptr_eq(&content(&d2, &[1])));
+        assert!(content(&d1, &[0]).ptr_eq(&content(&d2, &[0])));
+        assert!(content(&d1, &[1, 1]).ptr_eq(&content(&d2, &[1, 1])));
+    }
+
+    #[test]
+    fn test_structural_sharing_depth() {
+        // Three paragraphs in each of three blockquotes, nested `depth` times
+        fn level(depth: usize) -> Vec<MarkdownNode> {
+            match depth {
+                1 => (0..3).map(|_| p("text")).collect(),
+                _ => (0..3).map(|_| blockquote(level(depth - 1))).collect(),
+            }
+        }
+        // The nodes of `new` that don't share their content with the node at the same place in
+        // `old`
+        fn copied(old: &MarkdownNode, new: &MarkdownNode) -> usize {
+            match (old.content(), new.content()) {
+                (Some(a), Some(b)) if !a.ptr_eq(b) => {
+                    let children = a.children().iter().zip(b.children());
+                    1 + children.map(|(a, b)| copied(a, b)).sum::<usize>()
+                }
+                _ => 0,
+            }
+        }
+
+        for depth in 1..=4 {
+            let d1 = doc(level(depth));
+            // Insert into the last paragraph
+            let at = d1.content_size() - depth - 2;
+            let step = Step::<MD>::Replace(ReplaceStep {
+                span: Span { from: at, to: at },
+                slice: Slice::new(Fragment::from((MarkdownNode::text("x"),)), 0, 0),
+                structure: false,
+            });
+            let d2 = step.apply(&d1).unwrap();
+            assert_eq!(d2.content_size(), d1.content_size() + 1);
+            // Only the doc and the nodes on the path to the paragraph are copied
+            assert_eq!(copied(&d1, &d2), depth + 1);
+        }
+    }
+
     #[test]
     fn test_deserialize() {
         let s1: Step<MD> = serde_json::from_str(
@@ -80,6 +203,17 @@ mod tests {
             })
         );
 
+        let json = r#"{"stepType":"removeMark","from":3,"to":5,"mark":{"type":"strikethrough"}}"#;
+        let s3: Step<MD> = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            s3,
+            Step::RemoveMark(RemoveMarkStep {
+                span: Span { from: 3, to: 5 },
+                mark: MarkdownMark::Strikethrough,
+            })
+        );
+        assert_eq!(serde_json::to_string(&s3).unwrap(), json);
+
         let s2: Step<MD> = serde_json::from_str(
             r#"{"stepType":"replace","from":986,"to":986,"slice":{"content":[{"type":"text","text":"!"}]}}"#
         ).unwrap();
@@ -97,4 +231,539 @@ mod tests {
             })
         );
     }
+
+    // The `test-step` suite of prosemirror-transform
+
+    fn mk_step(from: usize, to: usize, val: Option<&str>) -> Step<MD> {
+        let span = Span { from, to };
+        match val {
+            Some("+em") => Step::AddMark(AddMarkStep {
+                span,
+                mark: MarkdownMark::Em,
+            }),
+            Some("-em") => Step::RemoveMark(RemoveMarkStep {
+                span,
+                mark: MarkdownMark::Em,
+            }),
+            Some(text) => Step::Replace(ReplaceStep {
+                span,
+                slice: Slice::new(Fragment::from(vec![MarkdownNode::text(text)]), 0, 0),
+                structure: false,
+            }),
+            None => Step::Replace(ReplaceStep {
+                span,
+                slice: Slice::default(),
+                structure: false,
+            }),
+        }
+    }
+
+    type StepArgs<'a> = (usize, usize, Option<&'a str>);
+
+    fn merges(a: StepArgs, b: StepArgs) -> bool {
+        let test_doc = doc!(p!("foobar")).node;
+        let (step1, step2) = (mk_step(a.0, a.1, a.2), mk_step(b.0, b.1, b.2));
+        match step1.merge(&step2) {
+            Some(merged) => {
+                let expected = step2.apply(&step1.apply(&test_doc).unwrap()).unwrap();
+                assert_eq!(merged.apply(&test_doc).unwrap(), expected);
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        assert!(merges((2, 2, Some("a")), (3, 3, Some("b"))), "typing");
+        assert!(
+            merges((2, 2, Some("a")), (2, 2, Some("b"))),
+            "inverse typing"
+        );
+        assert!(
+            !merges((2, 2, Some("a")), (4, 4, Some("b"))),
+            "separated typing"
+        );
+        assert!(
+            !merges((3, 3, Some("a")), (2, 2, Some("b"))),
+            "inverted separated typing"
+        );
+        assert!(merges((3, 4, None), (2, 3, None)), "adjacent backspaces");
+        assert!(merges((2, 3, None), (2, 3, None)), "adjacent deletes");
+        assert!(!merges((1, 2, None), (2, 3, None)), "separate backspaces");
+        assert!(
+            merges((2, 3, None), (2, 2, Some("x"))),
+            "backspace and type"
+        );
+        assert!(
+            merges((2, 2, Some("quux")), (6, 6, Some("baz"))),
+            "longer inserts"
+        );
+        assert!(
+            merges((2, 2, Some("quux")), (2, 2, Some("baz"))),
+            "inverted inserts"
+        );
+        assert!(merges((2, 5, None), (2, 4, None)), "longer deletes");
+        assert!(
+            merges((4, 6, None), (2, 4, None)),
+            "inverted longer deletes"
+        );
+        assert!(merges((3, 4, Some("x")), (4, 5, Some("y"))), "overwrites");
+        assert!(
+            merges((1, 2, Some("+em")), (2, 4, Some("+em"))),
+            "adjacent styles"
+        );
+        assert!(
+            merges((1, 3, Some("+em")), (2, 4, Some("+em"))),
+            "overlapping styles"
+        );
+        assert!(
+            !merges((1, 2, Some("+em")), (3, 4, Some("+em"))),
+            "separate styles"
+        );
+        assert!(
+            merges((1, 2, Some("-em")), (2, 4, Some("-em"))),
+            "removing adjacent"
+        );
+        assert!(
+            merges((1, 3, Some("-em")), (2, 4, Some("-em"))),
+            "removing overlapping"
+        );
+        assert!(
+            !merges((1, 2, Some("-em")), (3, 4, Some("-em"))),
+            "removing separate"
+        );
+    }
+
+    // The parts of the `test-trans` suite of prosemirror-transform that can be expressed as
+    // steps. Every case applies the steps that the corresponding `Transform` method produces.
+
+    fn test_transform(d: TaggedNode<MD>, steps: &[Step<MD>], expect: TaggedNode<MD>) {
+        let mut result = d.node.clone();
+        for step in steps {
+            result = step.apply(&result).unwrap();
+        }
+        assert_eq!(result, expect.node);
+        for (name, &pos) in expect.tags() {
+            let mapped = steps
+                .iter()
+                .fold(d.tag(name), |pos, step| step.get_map().map(pos, 1));
+            assert_eq!(mapped, pos, "tag <{}>", name);
+        }
+    }
+
+    fn add_mark(d: &TaggedNode<MD>, mark: MarkdownMark) -> Step<MD> {
+        Step::AddMark(AddMarkStep {
+            span: Span {
+                from: d.tag("a"),
+                to: d.tag("b"),
+            },
+            mark,
+        })
+    }
+
+    fn remove_mark(d: &TaggedNode<MD>, mark: MarkdownMark) -> Step<MD> {
+        Step::RemoveMark(RemoveMarkStep {
+            span: Span {
+                from: d.tag("a"),
+                to: d.tag("b"),
+            },
+            mark,
+        })
+    }
+
+    fn link(href: &str) -> MarkdownMark {
+        MarkdownMark::Link {
+            attrs: LinkAttrs {
+                href: href.to_owned(),
+                title: String::new(),
+            },
+        }
+    }
+
+    fn replace(from: usize, to: usize, slice: Slice<MD>, structure: bool) -> Step<MD> {
+        Step::Replace(ReplaceStep {
+            span: Span { from, to },
+            slice,
+            structure,
+        })
+    }
+
+    fn replace_around(
+        (from, to): (usize, usize),
+        (gap_from, gap_to): (usize, usize),
+        slice: Slice<MD>,
+        insert: usize,
+    ) -> Step<MD> {
+        Step::ReplaceAround(ReplaceAroundStep {
+            span: Span { from, to },
+            gap_from,
+            gap_to,
+            slice,
+            insert,
+            structure: true,
+        })
+    }
+
+    fn nodes(nodes: Vec<TaggedNode<MD>>) -> Fragment<MD> {
+        Fragment::from(nodes.into_iter().map(|n| n.node).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_add_mark() {
+        let d = doc!(p!("hello <a>there<b>!"));
+        let step = add_mark(&d, MarkdownMark::Strong);
+        test_transform(d, &[step], doc!(p!("hello ", strong!("there"), "!")));
+
+        let d = doc!(p!("hello ", strong!("<a>there"), "!<b>"));
+        let step = add_mark(&d, MarkdownMark::Strong);
+        test_transform(d, &[step], doc!(p!("hello ", strong!("there!"))));
+
+        let d = doc!(p!("one <a>two ", em!("three<b> four")));
+        let step = add_mark(&d, MarkdownMark::Strong);
+        let expect = doc!(p!("one ", strong!("two ", em!("three")), em!(" four")));
+        test_transform(d, &[step], expect);
+
+        let d = doc!(p!("this is a ", a!(href = "foo"; "<a>link<b>")));
+        let step = add_mark(&d, link("bar"));
+        test_transform(d, &[step], doc!(p!("this is a ", a!(href = "bar"; "link"))));
+
+        let d = doc!(
+            p!("before"),
+            blockquote!(p!("the variable is called <a>i<b>")),
+            p!("after")
+        );
+        let step = add_mark(&d, MarkdownMark::Code);
+        let expect = doc!(
+            p!("before"),
+            blockquote!(p!("the variable is called ", code!("i"))),
+            p!("after")
+        );
+        test_transform(d, &[step], expect);
+
+        let d = doc!(
+            p!("hi <a>this"),
+            blockquote!(p!("is")),
+            p!("a docu<b>ment"),
+            p!("!")
+        );
+        let step = add_mark(&d, MarkdownMark::Em);
+        let expect = doc!(
+            p!("hi ", em!("this")),
+            blockquote!(p!(em!("is"))),
+            p!(em!("a docu"), "ment"),
+            p!("!")
+        );
+        test_transform(d, &[step], expect);
+    }
+
+    #[test]
+    fn test_remove_mark() {
+        let d = doc!(p!(em!("hello <a>world<b>!")));
+        let step = remove_mark(&d, MarkdownMark::Em);
+        test_transform(d, &[step], doc!(p!(em!("hello "), "world", em!("!"))));
+
+        let d = doc!(p!(em!("hello"), " <a>world<b>!"));
+        let step = remove_mark(&d, MarkdownMark::Em);
+        test_transform(d, &[step], doc!(p!(em!("hello"), " <a>world<b>!")));
+
+        let d = doc!(p!(em!("one ", strong!("<a>two<b>"), " three")));
+        let step = remove_mark(&d, MarkdownMark::Strong);
+        test_transform(d, &[step], doc!(p!(em!("one two three"))));
+
+        let d = doc!(p!("<a>hello ", a!(href = "foo"; "link<b>")));
+        let step = remove_mark(&d, link("foo"));
+        test_transform(d, &[step], doc!(p!("hello link")));
+
+        let d = doc!(p!("<a>hello ", a!(href = "foo"; "link<b>")));
+        let step = remove_mark(&d, link("bar"));
+        test_transform(d, &[step], doc!(p!("hello ", a!(href = "foo"; "link"))));
+
+        let d = doc!(
+            blockquote!(p!(em!("much <a>em")), p!(em!("here too"))),
+            p!("between", em!("...")),
+            p!(em!("end<b>"))
+        );
+        let step = remove_mark(&d, MarkdownMark::Em);
+        let expect = doc!(
+            blockquote!(p!(em!("much "), "em"), p!("here too")),
+            p!("between..."),
+            p!("end")
+        );
+        test_transform(d, &[step], expect);
+
+        // Removing all marks takes a step per mark
+        let d = doc!(p!(
+            "<a>hello, ",
+            em!(
+                "this is ",
+                strong!("much"),
+                " ",
+                a!(href = "foo"; "markup<b>")
+            )
+        ));
+        let steps = [
+            remove_mark(&d, MarkdownMark::Em),
+            remove_mark(&d, MarkdownMark::Strong),
+            remove_mark(&d, link("foo")),
+        ];
+        test_transform(d, &steps, doc!(p!("<a>hello, this is much markup")));
+    }
+
+    fn insert(d: &TaggedNode<MD>, content: Vec<TaggedNode<MD>>) -> Step<MD> {
+        let pos = d.tag("a");
+        replace(pos, pos, Slice::new(nodes(content), 0, 0), false)
+    }
+
+    #[test]
+    fn test_insert() {
+        let d = doc!(p!("hello<a>there"));
+        let step = insert(&d, vec![br!()]);
+        test_transform(d, &[step], doc!(p!("hello", br!(), "<a>there")));
+
+        let d = doc!(p!("one"), "<a>", p!("two<2>"));
+        let step = insert(&d, vec![p!()]);
+        test_transform(d, &[step], doc!(p!("one"), p!(), "<a>", p!("two<2>")));
+
+        let d = doc!(p!("one"), "<a>", p!("two<2>"));
+        let step = insert(&d, vec![p!("hi"), hr!()]);
+        let expect = doc!(p!("one"), p!("hi"), hr!(), "<a>", p!("two<2>"));
+        test_transform(d, &[step], expect);
+
+        let d = doc!(blockquote!(p!("he<before>y"), "<a>"), p!("after<after>"));
+        let step = insert(&d, vec![p!()]);
+        let expect = doc!(blockquote!(p!("he<before>y"), p!()), p!("after<after>"));
+        test_transform(d, &[step], expect);
+
+        let d = doc!(blockquote!("<a>", p!("he<1>y")), p!("after<2>"));
+        let step = insert(&d, vec![p!()]);
+        let expect = doc!(blockquote!(p!(), "<a>", p!("he<1>y")), p!("after<2>"));
+        test_transform(d, &[step], expect);
+
+        // code blocks take plain text, but no marks
+        let d = doc!(pre!("fo<a>o"));
+        let text = Slice::new(Fragment::from(vec![MarkdownNode::text("x")]), 0, 0);
+        let step = replace(d.tag("a"), d.tag("a"), text, false);
+        test_transform(d, &[step], doc!(pre!("fox<a>o")));
+
+        let d = doc!(pre!("fo<a>o"));
+        let marked = p!(em!("x")).node.content().unwrap().clone();
+        let step = replace(d.tag("a"), d.tag("a"), Slice::new(marked, 0, 0), false);
+        assert!(step.apply(&d.node).is_err());
+    }
+
+    #[test]
+    fn test_delete() {
+        let d = doc!(p!("<1>one"), "<a>", p!("tw<2>o"), "<b>", p!("<3>three"));
+        let step = replace(d.tag("a"), d.tag("b"), Slice::default(), false);
+        test_transform(d, &[step], doc!(p!("<1>one"), "<a><2>", p!("<3>three")));
+
+        let d = doc!(blockquote!(p!("a"), "<a>", p!("b"), "<b>"), p!("c<1>"));
+        let step = replace(d.tag("a"), d.tag("b"), Slice::default(), false);
+        test_transform(d, &[step], doc!(blockquote!(p!("a")), p!("c<1>")));
+    }
+
+    fn join(d: &TaggedNode<MD>) -> Step<MD> {
+        let pos = d.tag("a");
+        replace(pos - 1, pos + 1, Slice::default(), true)
+    }
+
+    #[test]
+    fn test_join() {
+        let d = doc!(
+            blockquote!(p!("<before>a")),
+            "<a>",
+            blockquote!(p!("b")),
+            p!("after<after>")
+        );
+        let step = join(&d);
+        let expect = doc!(
+            blockquote!(p!("<before>a"), "<a>", p!("b")),
+            p!("after<after>")
+        );
+        test_transform(d, &[step], expect);
+
+        let d = doc!(h1!("foo"), "<a>", p!("bar"));
+        let step = join(&d);
+        test_transform(d, &[step], doc!(h1!("foobar")));
+
+        let d = doc!(blockquote!(
+            blockquote!(p!("a"), p!("b<before>")),
+            "<a>",
+            blockquote!(p!("c"), p!("d<after>"))
+        ));
+        let step = join(&d);
+        let expect = doc!(blockquote!(blockquote!(
+            p!("a"),
+            p!("b<before>"),
+            "<a>",
+            p!("c"),
+            p!("d<after>")
+        )));
+        test_transform(d, &[step], expect);
+
+        let d = doc!(
+            ol!(li!(p!("one")), li!(p!("two"))),
+            "<a>",
+            ol!(li!(p!("three")))
+        );
+        let step = join(&d);
+        let expect = doc!(ol!(li!(p!("one")), li!(p!("two")), "<a>", li!(p!("three"))));
+        test_transform(d, &[step], expect);
+
+        let d = doc!(ol!(li!(p!("one")), li!(p!("two")), "<a>", li!(p!("three"))));
+        let step = join(&d);
+        let expect = doc!(ol!(li!(p!("one")), li!(p!("two"), "<a>", p!("three"))));
+        test_transform(d, &[step], expect);
+
+        let d = doc!(p!("foo"), "<a>", p!("bar"));
+        let step = join(&d);
+        test_transform(d, &[step], doc!(p!("foo<a>bar")));
+    }
+
+    fn split(d: &TaggedNode<MD>, depth: usize) -> Step<MD> {
+        let pos = d.tag("a");
+        let rp = d.resolve(pos).unwrap();
+        let (mut before, mut after) = (Fragment::new(), Fragment::new());
+        for level in (rp.depth + 1 - depth..=rp.depth).rev() {
+            before = Fragment::from(vec![rp.node(level).copy(|_| before)]);
+            after = Fragment::from(vec![rp.node(level).copy(|_| after)]);
+        }
+        let slice = Slice::new(before.append(after), depth, depth);
+        replace(pos, pos, slice, true)
+    }
+
+    #[test]
+    fn test_split() {
+        let d = doc!(p!("foo<a>bar"));
+        let step = split(&d, 1);
+        test_transform(d, &[step], doc!(p!("foo"), p!("<a>bar")));
+
+        let d = doc!(p!("<1>a"), p!("<2>foo<a>bar<3>"), p!("<4>b"));
+        let step = split(&d, 1);
+        let expect = doc!(p!("<1>a"), p!("<2>foo"), p!("<a>bar<3>"), p!("<4>b"));
+        test_transform(d, &[step], expect);
+
+        let d = doc!(blockquote!(blockquote!(p!("foo<a>bar"))), p!("after<1>"));
+        let step = split(&d, 2);
+        let expect = doc!(
+            blockquote!(blockquote!(p!("foo")), blockquote!(p!("<a>bar"))),
+            p!("after<1>")
+        );
+        test_transform(d, &[step], expect);
+
+        let d = doc!(blockquote!(blockquote!(p!("foo<a>bar"))), p!("after<1>"));
+        let step = split(&d, 3);
+        let expect = doc!(
+            blockquote!(blockquote!(p!("foo"))),
+            blockquote!(blockquote!(p!("<a>bar"))),
+            p!("after<1>")
+        );
+        test_transform(d, &[step], expect);
+
+        let d = doc!(blockquote!(p!("hi<a>")));
+        let step = split(&d, 1);
+        test_transform(d, &[step], doc!(blockquote!(p!("hi"), p!("<a>"))));
+
+        let d = doc!(blockquote!(p!("<a>hi")));
+        let step = split(&d, 1);
+        test_transform(d, &[step], doc!(blockquote!(p!(), p!("<a>hi"))));
+
+        let d = doc!(ol!(
+            li!(p!("one<1>")),
+            li!(p!("two<a>three")),
+            li!(p!("four<2>"))
+        ));
+        let step = split(&d, 2);
+        let expect = doc!(ol!(
+            li!(p!("one<1>")),
+            li!(p!("two")),
+            li!(p!("<a>three")),
+            li!(p!("four<2>"))
+        ));
+        test_transform(d, &[step], expect);
+
+        // With a different type after the split
+        let d = doc!(h1!("hell<a>o!"));
+        let slice = Slice::new(nodes(vec![h1!(), p!()]), 1, 1);
+        let step = replace(d.tag("a"), d.tag("a"), slice, true);
+        test_transform(d, &[step], doc!(h1!("hell"), p!("<a>o!")));
+    }
+
+    #[test]
+    fn test_lift() {
+        let d = doc!(blockquote!(
+            p!("<before>one"),
+            p!("<a>two"),
+            p!("<after>three")
+        ));
+        let slice = Slice::new(nodes(vec![blockquote!(), blockquote!()]), 1, 1);
+        let step = replace_around((6, 11), (6, 11), slice, 1);
+        let expect = doc!(
+            blockquote!(p!("<before>one")),
+            p!("<a>two"),
+            blockquote!(p!("<after>three"))
+        );
+        test_transform(d, &[step], expect);
+
+        let d = doc!(blockquote!(p!("<a>one"), p!("<after>two")));
+        let slice = Slice::new(nodes(vec![blockquote!()]), 0, 1);
+        let step = replace_around((0, 6), (1, 6), slice, 0);
+        let expect = doc!(p!("<a>one"), blockquote!(p!("<after>two")));
+        test_transform(d, &[step], expect);
+
+        let d = doc!(blockquote!(p!("<a>one")), p!("after<1>"));
+        let step = replace_around((0, 7), (1, 6), Slice::default(), 0);
+        test_transform(d, &[step], doc!(p!("<a>one"), p!("after<1>")));
+    }
+
+    #[test]
+    fn test_wrap() {
+        let d = doc!(p!("one"), p!("<a>two"), p!("three"));
+        let slice = Slice::new(nodes(vec![blockquote!()]), 0, 0);
+        let step = replace_around((5, 10), (5, 10), slice, 1);
+        let expect = doc!(p!("one"), blockquote!(p!("<a>two")), p!("three"));
+        test_transform(d, &[step], expect);
+
+        let d = doc!(p!("one<1>"), p!("<a>two"), p!("<b>three"), p!("four<4>"));
+        let slice = Slice::new(nodes(vec![blockquote!()]), 0, 0);
+        let step = replace_around((5, 17), (5, 17), slice, 1);
+        let expect = doc!(
+            p!("one<1>"),
+            blockquote!(p!("<a>two"), p!("three")),
+            p!("four<4>")
+        );
+        test_transform(d, &[step], expect);
+
+        let d = doc!(p!("<a>one"), p!("<b>two"));
+        let slice = Slice::new(nodes(vec![ol!(li!())]), 0, 0);
+        let step = replace_around((0, 10), (0, 10), slice, 2);
+        test_transform(d, &[step], doc!(ol!(li!(p!("<a>one"), p!("<b>two")))));
+    }
+
+    fn set_block_type(pos: usize, size: usize, node: TaggedNode<MD>) -> Step<MD> {
+        let slice = Slice::new(nodes(vec![node]), 0, 0);
+        replace_around((pos, pos + size), (pos + 1, pos + size - 1), slice, 1)
+    }
+
+    #[test]
+    fn test_set_block_type() {
+        let d = doc!(p!("am<a> i"));
+        let step = set_block_type(0, 6, h2!());
+        test_transform(d, &[step], doc!(h2!("am<a> i")));
+
+        let d = doc!(h1!("<a>hello"), p!("there"), p!("<b>you"), p!("end"));
+        let steps = [
+            set_block_type(0, 7, pre!()),
+            set_block_type(7, 7, pre!()),
+            set_block_type(14, 5, pre!()),
+        ];
+        let expect = doc!(pre!("<a>hello"), pre!("there"), pre!("<b>you"), p!("end"));
+        test_transform(d, &steps, expect);
+
+        // Marks that aren't allowed in the new type are removed first
+        let d = doc!(p!("hello<a> ", em!("world")));
+        let steps = [mk_step(7, 12, Some("-em")), set_block_type(0, 13, pre!())];
+        test_transform(d, &steps, doc!(pre!("hello<a> world")));
+    }
 }
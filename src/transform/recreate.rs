@@ -0,0 +1,331 @@
+use super::{AddMarkStep, RemoveMarkStep, ReplaceAroundStep, ReplaceStep, Span, Step, Steps};
+use crate::model::{DiffEnd, Fragment, MarkSet, Node, Schema, Slice, SliceError};
+use displaydoc::Display;
+use thiserror::Error;
+
+/// Errors that can occur when computing the steps between two documents
+#[derive(Debug, Clone, PartialEq, Eq, Display, Error)]
+pub enum RecreateError {
+    /// Invalid slice
+    Slice(#[from] SliceError),
+}
+
+/// Compute a list of steps that transforms the document `old` into the document `new`.
+///
+/// Both documents are compared from the start and from the end to find the range that changed.
+/// If the only difference in that range is in the marks of inline content, it is expressed as
+/// `AddMarkStep`s and `RemoveMarkStep`s. If the content of the range is kept but (un)wrapped or
+/// re-typed, it is expressed as a single `ReplaceAroundStep`. Otherwise, the range is replaced
+/// with a `ReplaceStep`.
+pub fn recreate_steps<S: Schema>(old: &S::Node, new: &S::Node) -> Result<Steps<S>, RecreateError> {
+    let old_content = old.content().unwrap_or(Fragment::EMPTY_REF);
+    let new_content = new.content().unwrap_or(Fragment::EMPTY_REF);
+
+    let start = match old_content.find_diff_start(new_content) {
+        Some(start) => start,
+        None => return Ok(Vec::new()),
+    };
+    let DiffEnd { a: end_a, b: end_b } = old_content
+        .find_diff_end(new_content)
+        .expect("documents that differ at the start also differ at the end");
+
+    // If the scan from the end went past the start, move the end forward again
+    let overlap = start.saturating_sub(usize::min(end_a, end_b));
+    let (end_a, end_b) = (end_a + overlap, end_b + overlap);
+
+    let old_slice = old.slice(start..end_a, false)?;
+    let new_slice = new.slice(start..end_b, false)?;
+
+    if end_a == end_b && same_without_marks(&old_slice.content, &new_slice.content) {
+        return Ok(mark_steps(old_content, new_content, start, end_a));
+    }
+
+    let span = Span {
+        from: start,
+        to: end_a,
+    };
+    if let Some(step) = replace_around_step(span, &old_slice, &new_slice) {
+        return Ok(vec![Step::ReplaceAround(step)]);
+    }
+
+    Ok(vec![Step::Replace(ReplaceStep {
+        span,
+        slice: new_slice,
+        structure: false,
+    })])
+}
+
+fn strip_marks<S: Schema>(fragment: &Fragment<S>) -> Fragment<S> {
+    fragment
+        .children()
+        .iter()
+        .map(|child| child.mark(MarkSet::default()).copy(strip_marks))
+        .fold(Fragment::new(), |acc, child| {
+            acc.append(Fragment::from(vec![child]))
+        })
+}
+
+fn same_without_marks<S: Schema>(a: &Fragment<S>, b: &Fragment<S>) -> bool {
+    strip_marks(a) == strip_marks(b)
+}
+
+type InlineRange<'a, S> = (usize, usize, Option<&'a MarkSet<S>>);
+
+fn collect_inline<'a, S: Schema>(
+    fragment: &'a Fragment<S>,
+    start: usize,
+    from: usize,
+    to: usize,
+    target: &mut Vec<InlineRange<'a, S>>,
+) {
+    let mut pos = start;
+    for child in fragment.children() {
+        let end = pos + child.node_size();
+        if end > from && pos < to {
+            if child.is_inline() {
+                target.push((usize::max(pos, from), usize::min(end, to), child.marks()));
+            } else if let Some(content) = child.content() {
+                collect_inline(content, pos + 1, from, to, target);
+            }
+        }
+        pos = end;
+    }
+}
+
+fn push_mark_step<S: Schema>(steps: &mut Vec<Step<S>>, add: bool, mark: &S::Mark, span: Span) {
+    for step in steps.iter_mut().rev() {
+        let (step_span, step_mark) = match step {
+            Step::AddMark(AddMarkStep { span, mark }) if add => (span, mark),
+            Step::RemoveMark(RemoveMarkStep { span, mark }) if !add => (span, mark),
+            _ => continue,
+        };
+        if step_mark == mark && step_span.to == span.from {
+            step_span.to = span.to;
+            return;
+        }
+    }
+    let mark = mark.clone();
+    steps.push(if add {
+        Step::AddMark(AddMarkStep { span, mark })
+    } else {
+        Step::RemoveMark(RemoveMarkStep { span, mark })
+    });
+}
+
+fn mark_steps<S: Schema>(
+    old_content: &Fragment<S>,
+    new_content: &Fragment<S>,
+    from: usize,
+    to: usize,
+) -> Steps<S> {
+    let mut old_ranges = Vec::new();
+    let mut new_ranges = Vec::new();
+    collect_inline(old_content, 0, from, to, &mut old_ranges);
+    collect_inline(new_content, 0, from, to, &mut new_ranges);
+
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    let (mut i_old, mut i_new) = (0, 0);
+    while i_old < old_ranges.len() && i_new < new_ranges.len() {
+        let (old_from, old_to, old_marks) = old_ranges[i_old];
+        let (new_from, new_to, new_marks) = new_ranges[i_new];
+        let span = Span {
+            from: usize::max(old_from, new_from),
+            to: usize::min(old_to, new_to),
+        };
+
+        if span.from < span.to && old_marks != new_marks {
+            let none = MarkSet::default();
+            let old_marks = old_marks.unwrap_or(&none);
+            let new_marks = new_marks.unwrap_or(&none);
+            for mark in old_marks {
+                if !new_marks.contains(mark) {
+                    push_mark_step(&mut removed, false, mark, span);
+                }
+            }
+            for mark in new_marks {
+                if !old_marks.contains(mark) {
+                    push_mark_step(&mut added, true, mark, span);
+                }
+            }
+        }
+
+        if old_to <= new_to {
+            i_old += 1;
+        }
+        if new_to <= old_to {
+            i_new += 1;
+        }
+    }
+
+    removed.append(&mut added);
+    removed
+}
+
+/// Follow the nodes that are the only child of their parent, starting at the given fragment
+fn single_child_chain<S: Schema>(fragment: &Fragment<S>) -> Vec<&S::Node> {
+    let mut chain = Vec::new();
+    let mut current = fragment;
+    while current.child_count() == 1 {
+        let child = current.child(0);
+        match child.content() {
+            Some(content) => {
+                chain.push(child);
+                current = content;
+            }
+            None => break,
+        }
+    }
+    chain
+}
+
+fn replace_around_step<S: Schema>(
+    span: Span,
+    old_slice: &Slice<S>,
+    new_slice: &Slice<S>,
+) -> Option<ReplaceAroundStep<S>> {
+    let closed = |slice: &Slice<S>| slice.open_start == 0 && slice.open_end == 0;
+    if !closed(old_slice) || !closed(new_slice) {
+        return None;
+    }
+
+    let old_chain = single_child_chain(&old_slice.content);
+    let new_chain = single_child_chain(&new_slice.content);
+    let inner = |chain: &[&S::Node], slice: &Slice<S>, depth: usize| match depth {
+        0 => Some(slice.content.clone()),
+        _ => chain[depth - 1].content().cloned(),
+    };
+
+    // Prefer the shallowest pair of wrappers that keeps the content
+    for total in 1..=(old_chain.len() + new_chain.len()) {
+        for old_depth in total.saturating_sub(new_chain.len())..=total.min(old_chain.len()) {
+            let new_depth = total - old_depth;
+            let old_inner = inner(&old_chain, old_slice, old_depth);
+            if old_inner.is_none() || old_inner != inner(&new_chain, new_slice, new_depth) {
+                continue;
+            }
+
+            let wrappers = new_chain[..new_depth]
+                .iter()
+                .rev()
+                .fold(Fragment::new(), |content, node| {
+                    Fragment::from(vec![node.copy(|_| content)])
+                });
+            return Some(ReplaceAroundStep {
+                span,
+                gap_from: span.from + old_depth,
+                gap_to: span.to - old_depth,
+                slice: Slice::new(wrappers, 0, 0),
+                insert: new_depth,
+                structure: true,
+            });
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::recreate_steps;
+    use crate::markdown::{
+        helper::{blockquote, doc, em, h1, h2, li, node, p, strong, ul},
+        LinkAttrs, MarkdownMark, MarkdownNode, MD,
+    };
+    use crate::model::{Mark, Text, TextNode};
+    use crate::transform::{Step, Steps};
+
+    fn apply(old: &MarkdownNode, steps: &Steps<MD>) -> MarkdownNode {
+        steps
+            .iter()
+            .fold(old.clone(), |doc, step| step.apply(&doc).unwrap())
+    }
+
+    fn recreate(old: MarkdownNode, new: MarkdownNode) -> Steps<MD> {
+        let steps = recreate_steps::<MD>(&old, &new).unwrap();
+        assert_eq!(apply(&old, &steps), new);
+        steps
+    }
+
+    fn link(href: &str, text: &str) -> MarkdownNode {
+        let mark = MarkdownMark::Link {
+            attrs: LinkAttrs {
+                href: href.to_owned(),
+                title: String::new(),
+            },
+        };
+        MarkdownNode::Text(TextNode {
+            text: Text::from(text.to_owned()),
+            marks: mark.into_set(),
+        })
+    }
+
+    #[test]
+    fn identical_documents() {
+        let steps = recreate(doc(p("hello")), doc(p("hello")));
+        assert!(steps.is_empty());
+    }
+
+    #[test]
+    fn insert_and_delete_text() {
+        let steps = recreate(doc(p("aa")), doc(p("aaa")));
+        assert!(matches!(&steps[..], [Step::Replace(s)] if s.span.from == 3 && s.span.to == 3));
+
+        let steps = recreate(doc(p("hello world")), doc(p("hello")));
+        assert!(matches!(&steps[..], [Step::Replace(s)] if s.span.from == 6 && s.span.to == 12));
+
+        recreate(doc(p("one two")), doc(p("one three two")));
+    }
+
+    #[test]
+    fn structural_changes() {
+        recreate(doc(p("ab")), doc(vec![p("a"), p("b")]));
+        recreate(doc(vec![p("a"), p("b")]), doc(p("ab")));
+        recreate(doc(p("a")), doc(vec![p("a"), h1("b")]));
+        recreate(doc(vec![h1("x"), p("a")]), doc(vec![p("a")]));
+    }
+
+    #[test]
+    fn mark_changes() {
+        let steps = recreate(
+            doc(p("hello world")),
+            doc(p(vec![strong("hello"), node(" world")])),
+        );
+        assert_eq!(steps.len(), 1);
+        assert!(matches!(&steps[0], Step::AddMark(s) if s.span.from == 1 && s.span.to == 6));
+
+        let steps = recreate(
+            doc(p(vec![node("a "), em("b"), node(" c")])),
+            doc(p(vec![node("a "), strong("b"), node(" c")])),
+        );
+        assert_eq!(steps.len(), 2);
+        assert!(matches!(&steps[0], Step::RemoveMark(s) if s.mark == MarkdownMark::Em));
+        assert!(matches!(&steps[1], Step::AddMark(s) if s.mark == MarkdownMark::Strong));
+
+        let steps = recreate(
+            doc(p(vec![node("see "), link("a", "here")])),
+            doc(p(vec![node("see "), link("b", "here")])),
+        );
+        assert_eq!(steps.len(), 2);
+
+        let steps = recreate(
+            doc(vec![p("one"), p("two")]),
+            doc(vec![p(em("one")), p(em("two"))]),
+        );
+        assert!(steps.iter().all(|s| matches!(s, Step::AddMark(_))));
+    }
+
+    #[test]
+    fn wrap_and_lift() {
+        let steps = recreate(
+            doc(vec![p("x"), p("a")]),
+            doc(vec![p("x"), blockquote(p("a"))]),
+        );
+        assert!(matches!(&steps[..], [Step::ReplaceAround(s)] if s.insert == 1));
+
+        let steps = recreate(doc(ul(li(p("a")))), doc(p("a")));
+        assert!(matches!(&steps[..], [Step::ReplaceAround(s)] if s.gap_from == 2));
+
+        let steps = recreate(doc(vec![p("a"), h1("b")]), doc(vec![p("a"), h2("b")]));
+        assert!(matches!(&steps[..], [Step::ReplaceAround(s)] if s.insert == 1));
+    }
+}
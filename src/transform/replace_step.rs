@@ -1,11 +1,16 @@
-use super::{Span, StepError, StepKind, StepResult};
-use crate::model::{Node, ResolveErr, Schema, Slice};
+use super::{Span, StepError, StepKind, StepMap, StepResult};
+use crate::model::{replace, Node, ResolvedPos, Schema, Slice};
 use derivative::Derivative;
 use serde::{Deserialize, Serialize};
 
 /// Replace some part of the document
 #[derive(Derivative, Deserialize, Serialize)]
-#[derivative(Debug(bound = ""), PartialEq(bound = ""), Eq(bound = ""))]
+#[derivative(
+    Debug(bound = ""),
+    Clone(bound = ""),
+    PartialEq(bound = ""),
+    Eq(bound = "")
+)]
 #[serde(bound = "", rename_all = "camelCase")]
 pub struct ReplaceStep<S: Schema> {
     /// The affected span
@@ -19,22 +24,72 @@ pub struct ReplaceStep<S: Schema> {
     pub structure: bool,
 }
 
+impl<S: Schema> ReplaceStep<S> {
+    /// Try to merge this step with another one, to be applied directly after it. Returns the
+    /// merged step when possible, `None` if the steps can't be merged.
+    pub fn merge(&self, other: &Self) -> Option<Self> {
+        if self.structure || other.structure {
+            return None;
+        }
+        let (a, b) = (&self.slice, &other.slice);
+        let joined = |first: &Slice<S>, second: &Slice<S>| {
+            if first.size() + second.size() == 0 {
+                Slice::default()
+            } else {
+                let content = first.content.clone().append(second.content.clone());
+                Slice::new(content, first.open_start, second.open_end)
+            }
+        };
+        if self.span.from + a.size() == other.span.from && a.open_end == 0 && b.open_start == 0 {
+            Some(ReplaceStep {
+                span: Span {
+                    from: self.span.from,
+                    to: self.span.to + (other.span.to - other.span.from),
+                },
+                slice: joined(a, b),
+                structure: false,
+            })
+        } else if other.span.to == self.span.from && a.open_start == 0 && b.open_end == 0 {
+            Some(ReplaceStep {
+                span: Span {
+                    from: other.span.from,
+                    to: self.span.to,
+                },
+                slice: joined(b, a),
+                structure: false,
+            })
+        } else {
+            None
+        }
+    }
+}
+
 impl<S: Schema> StepKind<S> for ReplaceStep<S> {
     fn apply(&self, doc: &S::Node) -> StepResult<S> {
-        let from = self.span.from;
-        let to = self.span.to;
-        if self.structure && content_between::<S>(doc, from, to)? {
+        let rp_from = doc.resolve(self.span.from)?;
+        if self.structure && content_between(&rp_from, self.span.to) {
             Err(StepError::WouldOverwrite)
         } else {
-            let node = doc.replace(from..to, &self.slice)?;
+            let rp_to = doc.resolve(self.span.to)?;
+            let node = replace(&rp_from, &rp_to, &self.slice)?;
             Ok(node)
         }
     }
+
+    fn get_map(&self) -> StepMap {
+        let Span { from, to } = self.span;
+        StepMap::new(vec![from, to - from, self.slice.size()])
+    }
 }
 
 /// Replace the document structure while keeping some content
 #[derive(Derivative, Deserialize, Serialize)]
-#[derivative(Debug(bound = ""), PartialEq(bound = ""), Eq(bound = ""))]
+#[derivative(
+    Debug(bound = ""),
+    Clone(bound = ""),
+    PartialEq(bound = ""),
+    Eq(bound = "")
+)]
 #[serde(bound = "", rename_all = "camelCase")]
 pub struct ReplaceAroundStep<S: Schema> {
     /// The affected part of the document
@@ -56,14 +111,17 @@ pub struct ReplaceAroundStep<S: Schema> {
 
 impl<S: Schema> StepKind<S> for ReplaceAroundStep<S> {
     fn apply(&self, doc: &S::Node) -> StepResult<S> {
+        let rp_from = doc.resolve(self.span.from)?;
+        let rp_gap_to = doc.resolve(self.gap_to)?;
         if self.structure
-            && (content_between::<S>(doc, self.span.from, self.gap_from)?
-                || content_between::<S>(doc, self.gap_to, self.span.to)?)
+            && (content_between(&rp_from, self.gap_from)
+                || content_between(&rp_gap_to, self.span.to))
         {
             return Err(StepError::GapWouldOverwrite);
         }
 
-        let gap = doc.slice(self.gap_from..self.gap_to, false)?;
+        let rp_gap_from = doc.resolve(self.gap_from)?;
+        let gap = Slice::between(&rp_gap_from, &rp_gap_to, false);
         if gap.open_start != 0 || gap.open_end != 0 {
             return Err(StepError::GapNotFlat);
         }
@@ -71,14 +129,26 @@ impl<S: Schema> StepKind<S> for ReplaceAroundStep<S> {
         let inserted = self.slice.insert_at(self.insert, gap.content)?;
         let inserted = inserted.ok_or(StepError::GapNotFit)?;
 
-        let result = doc.replace(self.span.from..self.span.to, &inserted)?;
+        let rp_to = doc.resolve(self.span.to)?;
+        let result = replace(&rp_from, &rp_to, &inserted)?;
         Ok(result)
     }
+
+    fn get_map(&self) -> StepMap {
+        let Span { from, to } = self.span;
+        StepMap::new(vec![
+            from,
+            self.gap_from - from,
+            self.insert,
+            self.gap_to,
+            to - self.gap_to,
+            self.slice.size() - self.insert,
+        ])
+    }
 }
 
-fn content_between<S: Schema>(doc: &S::Node, from: usize, to: usize) -> Result<bool, ResolveErr> {
-    let rp_from = doc.resolve(from)?;
-    let mut dist = to - from;
+fn content_between<S: Schema>(rp_from: &ResolvedPos<S>, to: usize) -> bool {
+    let mut dist = to - rp_from.pos;
     let mut depth = rp_from.depth;
     while dist > 0 && depth > 0 && rp_from.index_after(depth) == rp_from.node(depth).child_count() {
         depth -= 1;
@@ -90,17 +160,17 @@ fn content_between<S: Schema>(doc: &S::Node, from: usize, to: usize) -> Result<b
             match next {
                 Some(c) => {
                     if c.is_leaf() {
-                        return Ok(true);
+                        return true;
                     } else {
                         next = c.first_child();
                         dist -= 1;
                     }
                 }
                 None => {
-                    return Ok(true);
+                    return true;
                 }
             }
         }
     }
-    Ok(false)
+    false
 }
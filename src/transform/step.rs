@@ -1,3 +1,4 @@
+use super::StepMap;
 use crate::model::{InsertError, ReplaceError, ResolveErr, Schema, SliceError};
 use derivative::Derivative;
 use displaydoc::Display;
@@ -40,4 +41,9 @@ pub trait StepKind<S: Schema> {
     /// applied to this document, or indicates success by containing a
     /// transformed document.
     fn apply(&self, doc: &S::Node) -> StepResult<S>;
+
+    /// Get the step map that represents the changes made by this step,
+    /// and which can be used to transform between positions in the old
+    /// and the new document.
+    fn get_map(&self) -> StepMap;
 }
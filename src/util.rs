@@ -48,3 +48,6 @@ pub(crate) fn then_some<T>(b: bool, v: T) -> Option<T> {
         None
     }
 }
+
+/// Fails to compile unless `T` can be cloned and shared between threads.
+pub(crate) fn assert_send_sync<T: Send + Sync + Clone>() {}
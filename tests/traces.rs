@@ -0,0 +1,148 @@
+//! Replays traces of steps in the JSON format of the JS implementation.
+//!
+//! Every `*.json` file in `tests/traces` contains an initial document, the steps that were
+//! applied to it, the resulting document and the steps that failed:
+//!
+//! ```json
+//! {
+//!   "doc": { "type": "doc", "content": [] },
+//!   "steps": [{ "stepType": "replace", "from": 3, "to": 3 }],
+//!   "result": { "type": "doc" },
+//!   "errors": [{ "step": 0, "message": "Position 3 out of range" }]
+//! }
+//! ```
+//!
+//! A failed step leaves the document unchanged, like `Transform::maybeStep` does. The documents
+//! must re-serialize to exactly the JSON that `JSON.stringify` produced for them.
+//!
+//! The checked-in fixtures are hand-written: their `result` and `errors` are what we expect
+//! `prosemirror-transform` to produce, not output recorded from it, so they don't prove agreement
+//! with the JS implementation. `tests/traces/record.mjs` overwrites `result` and `errors` with the
+//! output of `prosemirror-transform`; running it and committing the changes turns them into
+//! recorded traces.
+use prosemirror::markdown::{MarkdownNode, MD};
+use prosemirror::model::{Node, NodeType, ReplaceError, ResolveErr};
+use prosemirror::transform::{Step, StepError};
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use std::fs;
+use std::path::Path;
+
+#[derive(Deserialize)]
+struct Trace<'a> {
+    #[serde(borrow)]
+    doc: &'a RawValue,
+    steps: Vec<Step<MD>>,
+    #[serde(borrow)]
+    result: &'a RawValue,
+    #[serde(default)]
+    errors: Vec<TraceError>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct TraceError {
+    step: usize,
+    message: String,
+}
+
+/// Remove the whitespace outside of strings, which is how `JSON.stringify` prints
+fn minify(json: &str) -> String {
+    let mut out = String::with_capacity(json.len());
+    let (mut in_string, mut escaped) = (false, false);
+    for c in json.chars() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c.is_whitespace() {
+            continue;
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// The message of the error that the JS implementation reports for the failure
+fn js_message(err: &StepError<MD>) -> String {
+    match err {
+        StepError::Replace(ReplaceError::CannotJoin(sub, main)) => {
+            format!("Cannot join {} onto {}", sub.name(), main.name())
+        }
+        StepError::Replace(ReplaceError::InvalidContent(node_type)) => {
+            format!("Invalid content for node {}", node_type.name())
+        }
+        StepError::Resolve(ResolveErr::RangeError { pos })
+        | StepError::Replace(ReplaceError::Resolve(ResolveErr::RangeError { pos })) => {
+            format!("Position {} out of range", pos)
+        }
+        // The message of the replace error itself, not the one of its wrapper
+        StepError::Replace(err) => err.to_string(),
+        err => err.to_string(),
+    }
+}
+
+fn replay(path: &Path) -> Result<(), String> {
+    let src = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let trace: Trace = serde_json::from_str(&src).map_err(|e| e.to_string())?;
+
+    let mut doc: MarkdownNode = serde_json::from_str(trace.doc.get()).map_err(|e| e.to_string())?;
+    let initial = serde_json::to_string(&doc).unwrap();
+    if initial != minify(trace.doc.get()) {
+        return Err(format!("initial document re-serialized as {}", initial));
+    }
+
+    let mut errors = vec![];
+    for (index, step) in trace.steps.iter().enumerate() {
+        match step.apply(&doc) {
+            Ok(new_doc) => doc = new_doc,
+            Err(err) => errors.push(TraceError {
+                step: index,
+                message: js_message(&err),
+            }),
+        }
+    }
+    if errors != trace.errors {
+        return Err(format!(
+            "expected errors {:?}, got {:?}",
+            trace.errors, errors
+        ));
+    }
+    doc.check()
+        .map_err(|e| format!("invalid result: {:?}", e))?;
+
+    let (expected, actual) = (
+        minify(trace.result.get()),
+        serde_json::to_string(&doc).unwrap(),
+    );
+    if expected != actual {
+        return Err(format!("expected {}\n     got {}", expected, actual));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_traces() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/traces");
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no traces found");
+
+    let failures: Vec<_> = paths
+        .iter()
+        .filter_map(|path| {
+            replay(path)
+                .err()
+                .map(|e| format!("{}: {}", path.display(), e))
+        })
+        .collect();
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
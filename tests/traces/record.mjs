@@ -0,0 +1,43 @@
+// Records the `result` and `errors` of the traces in this directory with prosemirror-transform.
+//
+//     npm install prosemirror-model prosemirror-transform prosemirror-markdown
+//     node tests/traces/record.mjs
+//
+// Every `*.json` file needs a `doc` and the `steps` to apply to it. The document is normalized
+// through `Node.toJSON`, and `result` and `errors` are overwritten with what the JS
+// implementation produces. Steps that fail are skipped, like `Transform.maybeStep` does.
+import { readdirSync, readFileSync, writeFileSync } from "fs";
+import { dirname, join } from "path";
+import { fileURLToPath } from "url";
+import { Node } from "prosemirror-model";
+import { Step } from "prosemirror-transform";
+import { schema } from "prosemirror-markdown";
+
+const dir = dirname(fileURLToPath(import.meta.url));
+const files = readdirSync(dir)
+  .filter((file) => file.endsWith(".json"))
+  .sort();
+
+for (const file of files) {
+  const path = join(dir, file);
+  const trace = JSON.parse(readFileSync(path, "utf8"));
+  const initial = Node.fromJSON(schema, trace.doc);
+
+  let doc = initial;
+  const errors = [];
+  trace.steps.forEach((json, step) => {
+    try {
+      const result = Step.fromJSON(schema, json).apply(doc);
+      if (result.failed) errors.push({ step, message: result.failed });
+      else doc = result.doc;
+    } catch (e) {
+      // Positions out of range throw instead of failing the step
+      errors.push({ step, message: e.message });
+    }
+  });
+
+  const recorded = { doc: initial.toJSON(), steps: trace.steps, result: doc.toJSON() };
+  if (errors.length) recorded.errors = errors;
+  writeFileSync(path, JSON.stringify(recorded, null, 2) + "\n");
+  console.log(`${file}: ${trace.steps.length} steps, ${errors.length} failed`);
+}